@@ -0,0 +1,225 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use misisa::{snapshot, store::Schedules, ExcelData};
+use rust_xlsxwriter::{Workbook, XlsxError};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// MISIS has 8 institutes, each with a workbook of 4 courses
+const INSTITUTES: usize = 8;
+/// In 2022 there were no more than 26 groups on a page
+const GROUPS: usize = 26;
+const DAYS: [&str; 6] = [
+    "Понедельник",
+    "Вторник",
+    "Среда",
+    "Четверг",
+    "Пятница",
+    "Суббота",
+];
+const SUBJECTS: [&str; 24] = [
+    "Математический анализ",
+    "Линейная алгебра",
+    "Дискретная математика",
+    "Физика",
+    "Химия",
+    "Иностранный язык",
+    "Физическая культура и спорт",
+    "История России",
+    "Философия",
+    "Экономика",
+    "Программирование и алгоритмизация",
+    "Объектно-ориентированное программирование",
+    "Базы данных",
+    "Операционные системы",
+    "Компьютерные сети",
+    "Теория вероятностей и математическая статистика",
+    "Инженерная графика",
+    "Электротехника и электроника",
+    "Материаловедение",
+    "Теоретическая механика",
+    "Безопасность жизнедеятельности",
+    "Правоведение",
+    "Технологии обработки информации",
+    "Архитектура вычислительных систем",
+];
+const CLASS_TYPES: [&str; 3] = ["Лекционные", "Практические", "Лабораторные"];
+const SURNAMES: [&str; 20] = [
+    "Иванов",
+    "Смирнов",
+    "Кузнецов",
+    "Попова",
+    "Васильев",
+    "Петрова",
+    "Соколов",
+    "Михайлова",
+    "Новиков",
+    "Фёдоров",
+    "Морозова",
+    "Волков",
+    "Алексеева",
+    "Лебедев",
+    "Семёнов",
+    "Егорова",
+    "Павлов",
+    "Козлова",
+    "Степанов",
+    "Николаева",
+];
+const INITIALS: [char; 16] = [
+    'А', 'Б', 'В', 'Г', 'Д', 'Е', 'И', 'К', 'Л', 'М', 'Н', 'О', 'П', 'Р', 'С', 'Т',
+];
+const BUILDINGS: [&str; 5] = ["А", "Б", "В", "Г", "Л"];
+
+/// Deterministic pseudo random numbers, so every run benchmarks the same workbooks
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize % bound
+    }
+}
+
+/// Writes a workbook laid out like the ones MISIS publishes:
+/// a page per course, two columns (class and room) per subgroup and two rows (upper and lower week)
+/// per lesson, every group split into two subgroups
+fn write_workbook(path: &Path, random: &mut Random) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+    for course in 1..=4 {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(format!("{course} курс"))?;
+        sheet.write_string(0, 0, "День")?;
+        sheet.write_string(0, 1, "Пара")?;
+        sheet.write_string(0, 2, "Неделя")?;
+        for group in 0..GROUPS {
+            let column = (3 + group * 4) as u16;
+            let name = format!("БИВТ-{}-{}", 23 - course, group + 1);
+            sheet.write_string(0, column, name)?;
+            sheet.write_string(1, column, "1")?;
+            sheet.write_string(1, column + 2, "2")?;
+        }
+        for (day_number, day) in DAYS.iter().enumerate() {
+            for lesson in 0..7 {
+                for (parity, week) in ["верхняя", "нижняя"].iter().enumerate() {
+                    let row = (2 + (day_number * 7 + lesson) * 2 + parity) as u32;
+                    sheet.write_string(row, 0, *day)?;
+                    sheet.write_string(row, 1, (lesson + 1).to_string())?;
+                    sheet.write_string(row, 2, *week)?;
+                    for group in 0..GROUPS {
+                        // About four classes a day, most of them in the first lessons
+                        if lesson > 0 && random.below(7) < lesson + 1 {
+                            continue;
+                        }
+                        let class_type = CLASS_TYPES[random.below(CLASS_TYPES.len())];
+                        let class = format!(
+                            "{} ({class_type})\n{} {}. {}.",
+                            SUBJECTS[random.below(SUBJECTS.len())],
+                            SURNAMES[random.below(SURNAMES.len())],
+                            INITIALS[random.below(INITIALS.len())],
+                            INITIALS[random.below(INITIALS.len())],
+                        );
+                        let room = format!(
+                            "{}-{}{:02}",
+                            BUILDINGS[random.below(BUILDINGS.len())],
+                            random.below(9) + 1,
+                            random.below(40) + 1
+                        );
+                        // Lectures are shared by the subgroups, practice and labs aren't
+                        let subgroups: &[usize] = if class_type == "Лекционные" {
+                            &[0, 1]
+                        } else {
+                            &[random.below(2)]
+                        };
+                        for subgroup in subgroups {
+                            let column = (3 + group * 4 + subgroup * 2) as u16;
+                            sheet.write_string(row, column, &class)?;
+                            sheet.write_string(row, column + 1, &room)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    workbook.save(path)
+}
+
+/// Writes the workbooks, parses them into the json files and writes the snapshot
+fn prepare(dir: &Path) -> (Vec<PathBuf>, Schedules) {
+    let raw_dir = dir.join("raw");
+    let parsed_dir = dir.join("parsed");
+    std::fs::create_dir_all(&raw_dir).unwrap();
+    std::fs::create_dir_all(&parsed_dir).unwrap();
+    let mut random = Random(2022);
+    let mut workbooks = Vec::new();
+    for institute in 0..INSTITUTES {
+        let workbook = raw_dir.join(format!("institute_{institute}.xlsx"));
+        write_workbook(&workbook, &mut random).unwrap();
+        let parsed = ExcelData::open(&workbook).parse();
+        let file = File::create(parsed_dir.join(format!("institute_{institute}.json"))).unwrap();
+        serde_json::to_writer_pretty(BufWriter::new(file), &parsed).unwrap();
+        workbooks.push(workbook);
+    }
+    let schedules = Schedules::load_dir(&parsed_dir).unwrap();
+    let fingerprint = snapshot::fingerprint(&parsed_dir).unwrap();
+    snapshot::write(&snapshot::path(&parsed_dir), &schedules, fingerprint).unwrap();
+    (workbooks, schedules)
+}
+
+fn size(dir: &Path, extension: &str) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .map(|path| std::fs::metadata(path).unwrap().len())
+        .sum()
+}
+
+fn startup(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("misisa-bench-{}", std::process::id()));
+    let (workbooks, schedules) = prepare(&dir);
+    let parsed_dir = dir.join("parsed");
+    let snapshot_path = snapshot::path(&parsed_dir);
+    let fingerprint = snapshot::fingerprint(&parsed_dir).unwrap();
+    assert_eq!(
+        snapshot::read(&snapshot_path, fingerprint)
+            .unwrap()
+            .as_ref(),
+        Some(&schedules)
+    );
+    println!(
+        "{INSTITUTES} workbooks: {} bytes of xlsx, {} bytes of json, {} bytes of snapshot",
+        size(&dir.join("raw"), "xlsx"),
+        size(&parsed_dir, "json"),
+        size(&parsed_dir, "snapshot")
+    );
+
+    let mut group = c.benchmark_group("startup");
+    group.sample_size(10);
+    group.bench_function("parse a workbook", |b| {
+        b.iter(|| ExcelData::open(&workbooks[0]).parse());
+    });
+    group.bench_function("load json files", |b| {
+        b.iter(|| Schedules::load_dir(&parsed_dir).unwrap());
+    });
+    // The json files are looked at to know the snapshot is still theirs
+    group.bench_function("load snapshot", |b| {
+        b.iter(|| {
+            let fingerprint = snapshot::fingerprint(&parsed_dir).unwrap();
+            snapshot::read(&snapshot_path, fingerprint)
+                .unwrap()
+                .unwrap()
+        });
+    });
+    group.finish();
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
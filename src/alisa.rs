@@ -1,67 +1,78 @@
 #![warn(clippy::nursery, clippy::pedantic)]
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{Number, Value};
+use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Year {
-    year: Number,
+mod datetime;
+mod intent;
+mod response;
+
+pub use datetime::{next_weekday, relative_day, weekday, Resolved};
+pub use intent::{Intent, Slot, SlotValue, StringType};
+pub use response::{
+    Button, Card, CardFooter, CardHeader, CardItem, Response, ShowItemMeta, WebhookResponse,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Year {
+    pub year: Number,
     #[serde(default)]
-    year_is_relative: bool,
+    pub year_is_relative: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Month {
-    month: Number,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Month {
+    pub month: Number,
     #[serde(default)]
-    month_is_relative: bool, 
+    pub month_is_relative: bool, 
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Day {
-    day: Number,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Day {
+    pub day: Number,
     #[serde(default)]
-    day_is_relative: bool, 
+    pub day_is_relative: bool, 
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Hour{
-    hour: Number,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Hour{
+    pub hour: Number,
     #[serde(default)]
-    hour_is_relative: bool,
+    pub hour_is_relative: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Minute {
-    minute: Number,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Minute {
+    pub minute: Number,
     #[serde(default)]
-    minute_is_relative: bool,
+    pub minute_is_relative: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
-struct DateTime {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct DateTime {
     #[serde(flatten)]
-    year: Option<Year>,
+    pub year: Option<Year>,
     #[serde(flatten)]
-    month: Option<Month>,
+    pub month: Option<Month>,
     #[serde(flatten)]
-    day: Option<Day>,
+    pub day: Option<Day>,
     #[serde(flatten)]
-    hour: Option<Hour>,
+    pub hour: Option<Hour>,
     #[serde(flatten)]
-    minute: Option<Minute>,
+    pub minute: Option<Minute>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
-struct Fio {
-    first_name: Option<String>,
-    patronymic_name: Option<String>,
-    last_name: Option<String>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Fio {
+    pub first_name: Option<String>,
+    pub patronymic_name: Option<String>,
+    pub last_name: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)]
-enum Geolocation {
+pub enum Geolocation {
     Airport {
         airport: String
     },
@@ -75,14 +86,14 @@ enum Geolocation {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
-enum YandexNumber {
+pub enum YandexNumber {
     Integer(i64),
     Float(f64),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type", content = "value")]
-enum YandexEnteties {
+pub enum YandexEnteties {
     #[serde(rename = "YANDEX.FIO")]
     Fio(Fio),
     #[serde(rename = "YANDEX.GEO")]
@@ -203,45 +214,86 @@ enum EntityValue {
     Object(Value),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-struct Token {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Token {
     /// First word of a named entity
-    start: Number,
+    pub start: Number,
     /// First word after named entity
-    end: Number
+    pub end: Number
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 /// Named Entities
-struct Entity {
+pub struct Entity {
     /// Designation of the beginning and end of the named entity in the array of words.
     /// The numbering of words in the array starts from 0.
-    tokens: Token,
+    pub tokens: Token,
     #[serde(flatten)]
-    named_entity: YandexEnteties
+    pub named_entity: YandexEnteties
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 /// Words and entities which were extacted by Dialogs from user's request
-struct Nlu {
+pub struct Nlu {
     /// Words taken from user's phrase
-    tokens: Vec<String>,
+    #[serde(default)]
+    pub tokens: Vec<String>,
     /// Named entities
-    entities: Vec<Entity>,
-    /// Data extracted from user's request
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    /// Grammars from the skill's console that matched the request, by their names
     /// See [Natural language processing](https://yandex.ru/dev/dialogs/alice/doc/nlu.html)
-    intents: Value,
+    #[serde(default)]
+    pub intents: HashMap<String, Intent>,
+}
+
+/// Words that Dialogs recognise as a `YANDEX.DATETIME` with a relative day
+const RELATIVE_DAYS: [(&str, i64); 3] = [("сегодня", 0), ("завтра", 1), ("послезавтра", 2)];
+
+impl Nlu {
+    /// Words and entities of a text that didn't come through Dialogs, found the way they find them:
+    /// lowercase words without punctuation, numbers and relative days
+    #[must_use]
+    pub fn from_text(text: &str) -> Self {
+        let tokens: Vec<String> = text
+            .split_whitespace()
+            .map(|word| {
+                word.trim_matches(|char: char| !char.is_alphanumeric() && char != '-')
+                    .to_lowercase()
+            })
+            .filter(|word| !word.is_empty())
+            .collect();
+        let entities = tokens.iter().enumerate().filter_map(|(position, token)| {
+            let tokens = serde_json::json!({ "start": position, "end": position + 1 });
+            let entity = if let Ok(number) = token.parse::<i64>() {
+                serde_json::json!({ "tokens": tokens, "type": "YANDEX.NUMBER", "value": number })
+            } else {
+                let (_, day) = RELATIVE_DAYS.iter().find(|(word, _)| word == token)?;
+                serde_json::json!({
+                    "tokens": tokens,
+                    "type": "YANDEX.DATETIME",
+                    "value": { "day": day, "day_is_relative": true }
+                })
+            };
+            serde_json::from_value(entity).ok()
+        });
+        Self {
+            entities: entities.collect(),
+            tokens,
+            intents: HashMap::new(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 /// The formal characteristics of the replica that Yandex Dialogs managed to highlight
-struct Markup {
+pub struct Markup {
     /// A sign of a remark that contains criminal overtones (suicide, hate speech, threats). 
     /// You can set the skill to react in such cases, for example, to answer “I don’t understand what you mean. Please rephrase the question."
     ///
     /// Only `true` is possible. If the feature is not applicable, this property is not included in the response.
-    dangerous_context: bool,
+    pub dangerous_context: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -283,20 +335,55 @@ pub struct Request {
     /// in [`Request::command`], only the right part of the request will come: "what time is it".
     ///
     /// To get the exact request text, use the [`Request::original_utterance`] property.
-    command: String,
+    ///
+    /// Empty for the requests that aren't voice or text input.
+    #[serde(default)]
+    pub command: String,
     /// Full text of user request, max 1024 characters
     ///
     /// If the property contains the `"ping"` value,
     /// then the request is executed by Dialogs and is a test request.
-    original_utterance: String,
+    #[serde(default)]
+    pub original_utterance: String,
     /// The formal characteristics of the replica that Yandex Dialogs managed to highlight.
     /// The property is missing if none of the nested properties are applicable.
-    markup: Option<Markup>,
+    pub markup: Option<Markup>,
     /// The words and named entities that Dialogs retrieved from the user's query.
-    nlu: Nlu,
+    #[serde(default)]
+    pub nlu: Nlu,
+    /// Payload of the pressed button, see [`Button::payload`].
+    /// Present only for [`InputType::ButtonPressed`]
+    pub payload: Option<Value>,
     #[serde(rename = "type")]
     /// Input type.
-    request_type: InputType,
+    pub request_type: InputType,
+    /// Kind of the show Alice gathers the content for.
+    /// Present only for [`InputType::ShowPull`]
+    pub show_type: Option<ShowType>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// A show of Alice made of the content of several skills
+pub enum ShowType {
+    #[serde(rename = "MORNING")]
+    Morning,
+    /// A show Dialogs added after this one, so the request is still read
+    #[serde(other)]
+    Unknown,
+}
+
+impl Request {
+    /// Dialogs check that the skill is alive with a `ping` message
+    #[must_use]
+    pub fn is_ping(&self) -> bool {
+        self.original_utterance == "ping"
+    }
+
+    /// Payload of the pressed button, read as the type it was sent with
+    #[must_use]
+    pub fn payload<T: DeserializeOwned>(&self) -> Option<T> {
+        T::deserialize(self.payload.as_ref()?).ok()
+    }
 }
 
 #[test]
@@ -376,5 +463,267 @@ fn request_deserializes() {
     assert!(request.markup.unwrap().dangerous_context);
     assert_eq!(&request.nlu.tokens, &["закажи", "пиццу", "на", "льва", "толстого", "16", "на", "завтра"]);
     assert_eq!(request.nlu.entities.len(), 4);
-    assert_eq!(request.nlu.intents, json!({}));
+    assert!(request.nlu.intents.is_empty());
+    assert_eq!(request.payload, Some(json!({})));
+}
+
+#[test]
+fn markup_deserializes() {
+    use serde_json::json;
+    let json = json!({
+        "command": "",
+        "original_utterance": "",
+        "markup": {
+            "dangerous_context": true
+        },
+        "type": "SimpleUtterance"
+    });
+    let request: Request = serde_json::from_value(json).unwrap();
+    assert!(request.markup.unwrap().dangerous_context);
+
+    // Missing if there is nothing to mark
+    let json = json!({
+        "command": "привет",
+        "original_utterance": "Привет",
+        "type": "SimpleUtterance"
+    });
+    let request: Request = serde_json::from_value(json).unwrap();
+    assert_eq!(request.markup, None);
+}
+
+#[test]
+fn button_press_deserializes() {
+    use serde_json::json;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Payload {
+        day: u8,
+    }
+
+    let button = Button::with_payload("Завтра", &json!({ "day": 1 }));
+    let request: Request = serde_json::from_value(json!({
+        "nlu": { "tokens": [], "entities": [], "intents": {} },
+        "payload": button.payload,
+        "type": "ButtonPressed"
+    }))
+    .unwrap();
+    assert_eq!(request.request_type, InputType::ButtonPressed);
+    assert_eq!(request.payload(), Some(Payload { day: 1 }));
+    assert_eq!(request.payload::<u8>(), None);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+/// Interfaces that are available on the user's device.
+/// Each of them is present as an empty object if it is supported.
+pub struct Interfaces {
+    /// The user can see the response text and buttons
+    pub screen: Option<Value>,
+    /// The user can be asked to log in with an account on another service
+    pub account_linking: Option<Value>,
+    /// The device can play audio with the audio player
+    pub audio_player: Option<Value>,
+}
+
+impl Interfaces {
+    /// The device shows text, cards and buttons, unlike a smart speaker
+    #[must_use]
+    pub const fn has_screen(&self) -> bool {
+        self.screen.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Information about the device the user talks with
+pub struct Meta {
+    /// Language in POSIX format, e.g. `ru-RU`
+    pub locale: String,
+    /// Name of the user's time zone, e.g. `Europe/Moscow`
+    pub timezone: String,
+    /// Identifier of the device and application, don't rely on its format
+    pub client_id: String,
+    /// Interfaces that are available on the user's device
+    #[serde(default)]
+    pub interfaces: Interfaces,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A user that is logged in with a Yandex account
+pub struct User {
+    pub user_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// An instance of the Alice application the user talks with
+pub struct Application {
+    /// Identifier of the application on a particular device
+    pub application_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_field_names)]
+/// Data about the dialog
+pub struct Session {
+    /// Number of the message in the session, starting at 0
+    pub message_id: u64,
+    /// Unique identifier of the session
+    pub session_id: String,
+    /// Identifier of the skill, assigned in the Dialogs console
+    pub skill_id: String,
+    /// Missing if the user isn't logged in
+    pub user: Option<User>,
+    pub application: Application,
+    /// `true` if the user just started a dialog with the skill
+    pub new: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+/// Data stored by the skill, sent back with every request
+pub struct State {
+    /// Data kept during a session, see [`WebhookResponse::session_state`]
+    #[serde(default)]
+    pub session: Value,
+    /// Data kept for a logged in user, see [`WebhookResponse::user_state_update`]
+    #[serde(default)]
+    pub user: Value,
+    /// Data kept for an application instance, see [`WebhookResponse::application_state`]
+    #[serde(default)]
+    pub application: Value,
+}
+
+impl State {
+    /// Keeps the states the skill set for the next message, the way Dialogs do it
+    pub fn apply(&mut self, response: &WebhookResponse) {
+        self.session = response.session_state.clone().unwrap_or_default();
+        if let Some(application) = &response.application_state {
+            self.application = application.clone();
+        }
+        if let Some(Value::Object(update)) = &response.user_state_update {
+            if !self.user.is_object() {
+                self.user = Value::Object(serde_json::Map::new());
+            }
+            if let Value::Object(user) = &mut self.user {
+                for (key, value) in update {
+                    if value.is_null() {
+                        user.remove(key);
+                    } else {
+                        user.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Everything Dialogs send to the skill's webhook
+pub struct WebhookRequest {
+    pub meta: Meta,
+    pub request: Request,
+    pub session: Session,
+    #[serde(default)]
+    pub state: State,
+    /// Protocol version, currently `1.0`
+    pub version: String,
+}
+
+#[test]
+fn webhook_request_deserializes() {
+    use serde_json::json;
+    let json = json!({
+        "meta": {
+            "locale": "ru-RU",
+            "timezone": "Europe/Moscow",
+            "client_id": "ru.yandex.searchplugin/7.16 (none none; android 4.4.2)",
+            "interfaces": {
+                "screen": {},
+                "payments": {},
+                "account_linking": {}
+            }
+        },
+        "session": {
+            "message_id": 0,
+            "session_id": "2eac4854-fce721f3-b845abba-20d60",
+            "skill_id": "3ad36498-f5rd-4079-a14b-788652932056",
+            "user_id": "47C73714B580ED2469056E71081159529FFC676A4E5B059D629A819E857DC2F8",
+            "user": {
+                "user_id": "6C91DA5198D1758C6A9F63A7C5CDDF09359F683B13A18A151FBF4C8B092BB0C2"
+            },
+            "application": {
+                "application_id": "47C73714B580ED2469056E71081159529FFC676A4E5B059D629A819E857DC2F8"
+            },
+            "new": true
+        },
+        "request": {
+            "command": "когда первая пара завтра",
+            "original_utterance": "Когда первая пара завтра?",
+            "nlu": {
+                "tokens": ["когда", "первая", "пара", "завтра"],
+                "entities": [],
+                "intents": {}
+            },
+            "type": "SimpleUtterance"
+        },
+        "state": {
+            "session": {
+                "value": 10
+            },
+            "user": {
+                "value": 42
+            },
+            "application": {
+                "value": 37
+            }
+        },
+        "version": "1.0"
+    });
+    let request: WebhookRequest = serde_json::from_value(json).unwrap();
+    assert_eq!(request.meta.timezone, "Europe/Moscow");
+    assert!(request.meta.interfaces.screen.is_some());
+    assert!(request.meta.interfaces.audio_player.is_none());
+    assert!(request.session.new);
+    assert_eq!(
+        request.session.user.unwrap().user_id,
+        "6C91DA5198D1758C6A9F63A7C5CDDF09359F683B13A18A151FBF4C8B092BB0C2"
+    );
+    assert_eq!(request.request.command, "когда первая пара завтра");
+    assert_eq!(request.state.user, json!({ "value": 42 }));
+    assert_eq!(request.version, "1.0");
+}
+
+#[test]
+fn show_pull_deserializes() {
+    use serde_json::json;
+    let json = json!({
+        "meta": {
+            "locale": "ru-RU",
+            "timezone": "Europe/Moscow",
+            "client_id": "ru.yandex.quasar.app/1.0 (Yandex Station; android 6.0.1)",
+            "interfaces": {}
+        },
+        "session": {
+            "message_id": 0,
+            "session_id": "e6d1bb0f-5ad3-4a32-a6d2-0d3dcc3f1d5a",
+            "skill_id": "3ad36498-f5rd-4079-a14b-788652932056",
+            "application": {
+                "application_id": "47C73714B580ED2469056E71081159529FFC676A4E5B059D629A819E857DC2F8"
+            },
+            "new": true
+        },
+        "request": {
+            "type": "Show.Pull",
+            "show_type": "MORNING"
+        },
+        "version": "1.0"
+    });
+    let request: WebhookRequest = serde_json::from_value(json).unwrap();
+    assert_eq!(request.request.request_type, InputType::ShowPull);
+    assert_eq!(request.request.show_type, Some(ShowType::Morning));
+    assert!(request.request.command.is_empty());
+    assert!(request.session.user.is_none());
+    assert_eq!(request.state, State::default());
+
+    let mut evening = serde_json::to_value(&request).unwrap();
+    evening["request"]["show_type"] = json!("EVENING");
+    let request: WebhookRequest = serde_json::from_value(evening).unwrap();
+    assert_eq!(request.request.show_type, Some(ShowType::Unknown));
 }
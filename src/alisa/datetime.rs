@@ -0,0 +1,340 @@
+use super::DateTime;
+use chrono::{
+    Datelike, Days, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, Timelike, Weekday,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A moment or a period the user talks about, in the time zone of the "now" it was resolved with
+pub enum Resolved {
+    /// Hours or minutes were named, e.g. "через два часа" or "завтра в 9"
+    DateTime(NaiveDateTime),
+    /// A day was named, e.g. "завтра" or "5 марта"
+    Date(NaiveDate),
+    /// Only a month or a year was named, e.g. "в следующем месяце". Both ends are included
+    Range { start: NaiveDate, end: NaiveDate },
+}
+
+impl Resolved {
+    /// The first day of the resolved period
+    #[must_use]
+    pub const fn date(self) -> NaiveDate {
+        match self {
+            Self::DateTime(date_time) => date_time.date(),
+            Self::Date(date) => date,
+            Self::Range { start, .. } => start,
+        }
+    }
+}
+
+/// A date with the day clamped to the length of the month, so the 31st of February is the 28th
+fn clamped_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    first.with_day(day.min(last.day()))
+}
+
+/// Moves a date by a number of months, clamping the day to the length of the new month
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let magnitude = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months < 0 {
+        date.checked_sub_months(magnitude)
+    } else {
+        date.checked_add_months(magnitude)
+    }
+}
+
+/// The `day` of the month of the `date`, or of the nearest month after it that has such a day
+fn day_from(date: NaiveDate, day: u32) -> Option<NaiveDate> {
+    let first = date.with_day(1)?;
+    (0..12).find_map(|months| first.checked_add_months(Months::new(months))?.with_day(day))
+}
+
+impl DateTime {
+    /// Turns the entity into a concrete date, time or period.
+    /// Relative parts are added to `now`, absolute ones replace its parts,
+    /// and the missing parts coarser than the named ones are taken from `now`.
+    /// A month or a day named without a year that has passed is taken in the next year or month,
+    /// and a day named without a month is taken in the nearest month that has it.
+    ///
+    /// Returns [`None`] if nothing was named or the parts don't make a valid date
+    #[must_use]
+    pub fn resolve(&self, now: chrono::DateTime<FixedOffset>) -> Option<Resolved> {
+        let now = now.naive_local();
+        let mut date = now.date();
+
+        if let Some(year) = &self.year {
+            let value = year.year.as_i64()?;
+            date = if year.year_is_relative {
+                add_months(date, value.checked_mul(12)?)?
+            } else {
+                clamped_date(i32::try_from(value).ok()?, date.month(), date.day())?
+            };
+        }
+        if let Some(month) = &self.month {
+            let value = month.month.as_i64()?;
+            date = if month.month_is_relative {
+                add_months(date, value)?
+            } else {
+                clamped_date(date.year(), u32::try_from(value).ok()?, date.day())?
+            };
+        }
+        if let Some(day) = &self.day {
+            let value = day.day.as_i64()?;
+            date = if day.day_is_relative {
+                date.checked_add_signed(Duration::try_days(value)?)?
+            } else if self.month.is_none() {
+                // "31 числа" in September is the 31st of October
+                day_from(date, u32::try_from(value).ok()?)?
+            } else {
+                date.with_day(u32::try_from(value).ok()?)?
+            };
+        }
+        // Without a year, the named month or day that has passed already is the next one
+        if self.year.is_none() {
+            let today = now.date();
+            let absolute_month = self
+                .month
+                .as_ref()
+                .is_some_and(|month| !month.month_is_relative);
+            let absolute_day = self.day.as_ref().is_some_and(|day| !day.day_is_relative);
+            if absolute_month {
+                let passed = if absolute_day {
+                    date < today
+                } else {
+                    (date.year(), date.month()) < (today.year(), today.month())
+                };
+                if passed {
+                    date = clamped_date(date.year() + 1, date.month(), date.day())?;
+                }
+            } else if self.month.is_none() && absolute_day && date < today {
+                date = day_from(add_months(date.with_day(1)?, 1)?, date.day())?;
+            }
+        }
+
+        if self.hour.is_none() && self.minute.is_none() {
+            return if self.day.is_some() {
+                Some(Resolved::Date(date))
+            } else if self.month.is_some() {
+                let start = date.with_day(1)?;
+                let end = start.checked_add_months(Months::new(1))?.pred_opt()?;
+                Some(Resolved::Range { start, end })
+            } else if self.year.is_some() {
+                Some(Resolved::Range {
+                    start: NaiveDate::from_ymd_opt(date.year(), 1, 1)?,
+                    end: NaiveDate::from_ymd_opt(date.year(), 12, 31)?,
+                })
+            } else {
+                None
+            };
+        }
+
+        let mut date_time = date.and_time(now.time());
+        if let Some(hour) = &self.hour {
+            let value = hour.hour.as_i64()?;
+            date_time = if hour.hour_is_relative {
+                date_time.checked_add_signed(Duration::try_hours(value)?)?
+            } else {
+                // "В 9" means 9:00 sharp, unless the minutes are named too
+                date.and_hms_opt(u32::try_from(value).ok()?, 0, 0)?
+            };
+        }
+        if let Some(minute) = &self.minute {
+            let value = minute.minute.as_i64()?;
+            date_time = if minute.minute_is_relative {
+                date_time.checked_add_signed(Duration::try_minutes(value)?)?
+            } else {
+                date_time.date().and_time(
+                    date_time
+                        .time()
+                        .with_minute(u32::try_from(value).ok()?)?
+                        .with_second(0)?,
+                )
+            };
+        }
+        Some(Resolved::DateTime(date_time))
+    }
+}
+
+/// A day of the week named in any of the cases it's asked about with, e.g. "в среду"
+#[must_use]
+pub fn weekday(token: &str) -> Option<Weekday> {
+    match token {
+        "понедельник" | "понедельника" => Some(Weekday::Mon),
+        "вторник" | "вторника" => Some(Weekday::Tue),
+        "среда" | "среду" | "среды" => Some(Weekday::Wed),
+        "четверг" | "четверга" => Some(Weekday::Thu),
+        "пятница" | "пятницу" | "пятницы" => Some(Weekday::Fri),
+        "суббота" | "субботу" | "субботы" => Some(Weekday::Sat),
+        "воскресенье" | "воскресенья" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// The nearest day that is the `weekday`, `today` included
+#[must_use]
+pub fn next_weekday(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let days_ahead =
+        (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today.checked_add_days(Days::new(days_ahead.into()))
+}
+
+/// A day named by a single word, e.g. "завтра" or "пятницу"
+#[must_use]
+pub fn relative_day(token: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match token {
+        "сегодня" => Some(today),
+        "завтра" => today.succ_opt(),
+        "послезавтра" => today.checked_add_days(Days::new(2)),
+        "вчера" => today.pred_opt(),
+        _ => next_weekday(today, weekday(token)?),
+    }
+}
+
+#[cfg(test)]
+fn moscow_time(time: &str) -> chrono::DateTime<FixedOffset> {
+    chrono::DateTime::parse_from_rfc3339(&format!("{time}+03:00")).unwrap()
+}
+
+#[cfg(test)]
+fn date_time(value: serde_json::Value) -> DateTime {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn relative_days_roll_over() {
+    use serde_json::json;
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let tomorrow = date_time(json!({ "day": 1, "day_is_relative": true }));
+    let yesterday = date_time(json!({ "day": -1, "day_is_relative": true }));
+
+    let end_of_january = moscow_time("2023-01-31T12:00:00");
+    assert_eq!(
+        tomorrow.resolve(end_of_january),
+        Some(Resolved::Date(date(2023, 2, 1)))
+    );
+    let new_years_eve = moscow_time("2022-12-31T23:30:00");
+    assert_eq!(
+        tomorrow.resolve(new_years_eve),
+        Some(Resolved::Date(date(2023, 1, 1)))
+    );
+    let new_year = moscow_time("2023-01-01T00:10:00");
+    assert_eq!(
+        yesterday.resolve(new_year),
+        Some(Resolved::Date(date(2022, 12, 31)))
+    );
+    let leap_day = moscow_time("2024-02-28T08:00:00");
+    assert_eq!(
+        tomorrow.resolve(leap_day),
+        Some(Resolved::Date(date(2024, 2, 29)))
+    );
+}
+
+#[test]
+fn relative_months_and_years_roll_over() {
+    use serde_json::json;
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let december = moscow_time("2022-12-15T10:00:00");
+
+    let next_month = date_time(json!({ "month": 1, "month_is_relative": true }));
+    assert_eq!(
+        next_month.resolve(december),
+        Some(Resolved::Range {
+            start: date(2023, 1, 1),
+            end: date(2023, 1, 31)
+        })
+    );
+    // "5 числа следующего месяца"
+    let fifth = date_time(json!({ "month": 1, "month_is_relative": true, "day": 5 }));
+    assert_eq!(
+        fifth.resolve(december),
+        Some(Resolved::Date(date(2023, 1, 5)))
+    );
+    let next_year = date_time(json!({ "year": 1, "year_is_relative": true }));
+    assert_eq!(
+        next_year.resolve(december),
+        Some(Resolved::Range {
+            start: date(2023, 1, 1),
+            end: date(2023, 12, 31)
+        })
+    );
+    // "1 марта" in December is the next March
+    let first_of_march = date_time(json!({ "month": 3, "day": 1 }));
+    assert_eq!(
+        first_of_march.resolve(december),
+        Some(Resolved::Date(date(2023, 3, 1)))
+    );
+    let twentieth_of_december = date_time(json!({ "month": 12, "day": 20 }));
+    assert_eq!(
+        twentieth_of_december.resolve(december),
+        Some(Resolved::Date(date(2022, 12, 20)))
+    );
+    let in_march = date_time(json!({ "month": 3 }));
+    assert_eq!(
+        in_march.resolve(december),
+        Some(Resolved::Range {
+            start: date(2023, 3, 1),
+            end: date(2023, 3, 31)
+        })
+    );
+    // "1 марта 2022 года" stays in the past
+    let first_of_march_2022 = date_time(json!({ "year": 2022, "month": 3, "day": 1 }));
+    assert_eq!(
+        first_of_march_2022.resolve(december),
+        Some(Resolved::Date(date(2022, 3, 1)))
+    );
+    // "5 числа" on the 15th is the 5th of the next month
+    let fifth_of_month = date_time(json!({ "day": 5 }));
+    assert_eq!(
+        fifth_of_month.resolve(december),
+        Some(Resolved::Date(date(2023, 1, 5)))
+    );
+    let thirty_first_of_february = date_time(json!({ "month": 2, "day": 31 }));
+    assert_eq!(thirty_first_of_february.resolve(december), None);
+    // "31 числа" in September is in October, as September has no 31st
+    let thirty_first = date_time(json!({ "day": 31 }));
+    assert_eq!(
+        thirty_first.resolve(moscow_time("2022-09-15T10:00:00")),
+        Some(Resolved::Date(date(2022, 10, 31)))
+    );
+    // "30 числа" on the 31st of January has passed, and February has no 30th
+    let thirtieth = date_time(json!({ "day": 30 }));
+    assert_eq!(
+        thirtieth.resolve(moscow_time("2023-01-31T10:00:00")),
+        Some(Resolved::Date(date(2023, 3, 30)))
+    );
+}
+
+#[test]
+fn times_resolve() {
+    use serde_json::json;
+    let at = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+    let late = moscow_time("2022-12-31T23:30:00");
+
+    let in_an_hour = date_time(json!({ "hour": 1, "hour_is_relative": true }));
+    assert_eq!(
+        in_an_hour.resolve(late),
+        Some(Resolved::DateTime(at("2023-01-01 00:30:00")))
+    );
+    let tomorrow_at_nine = date_time(json!({ "day": 1, "day_is_relative": true, "hour": 9 }));
+    assert_eq!(
+        tomorrow_at_nine.resolve(late),
+        Some(Resolved::DateTime(at("2023-01-01 09:00:00")))
+    );
+    let at_half_past_ten = date_time(json!({ "hour": 10, "minute": 30 }));
+    assert_eq!(
+        at_half_past_ten.resolve(late),
+        Some(Resolved::DateTime(at("2022-12-31 10:30:00")))
+    );
+    assert_eq!(DateTime::default().resolve(late), None);
+}
+
+#[test]
+fn weekdays_resolve() {
+    let date = |day| NaiveDate::from_ymd_opt(2022, 9, day).unwrap();
+    // The 4th of September 2022 is Sunday
+    assert_eq!(relative_day("понедельник", date(4)), Some(date(5)));
+    assert_eq!(relative_day("воскресенье", date(4)), Some(date(4)));
+    assert_eq!(relative_day("субботу", date(4)), Some(date(10)));
+    assert_eq!(relative_day("завтра", date(4)), Some(date(5)));
+    assert_eq!(relative_day("пара", date(4)), None);
+}
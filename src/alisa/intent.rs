@@ -0,0 +1,149 @@
+use super::{Token, YandexEnteties};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+/// Value of a slot: a built-in entity, a string, or a value of an entity defined in the console
+pub enum SlotValue {
+    Entity(YandexEnteties),
+    /// `YANDEX.STRING`, any words
+    String {
+        #[serde(rename = "type")]
+        slot_type: StringType,
+        value: String,
+    },
+    /// An entity from the skill's console, e.g. `Weekday`
+    Custom {
+        #[serde(rename = "type")]
+        entity_type: String,
+        value: Value,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringType {
+    #[serde(rename = "YANDEX.STRING")]
+    String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A part of a grammar that was filled by the user's words
+pub struct Slot {
+    /// Words the slot was filled with, missing for the slots with a default value
+    pub tokens: Option<Token>,
+    #[serde(flatten)]
+    pub value: SlotValue,
+}
+
+impl Slot {
+    /// The built-in entity of the slot
+    #[must_use]
+    pub const fn entity(&self) -> Option<&YandexEnteties> {
+        match &self.value {
+            SlotValue::Entity(entity) => Some(entity),
+            _ => None,
+        }
+    }
+
+    /// The value of a `YANDEX.STRING` slot
+    #[must_use]
+    pub fn string(&self) -> Option<&str> {
+        match &self.value {
+            SlotValue::String { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value of a custom entity, read as the enum it was defined with
+    #[must_use]
+    pub fn custom<T: DeserializeOwned>(&self) -> Option<T> {
+        match &self.value {
+            SlotValue::Custom { value, .. } => T::deserialize(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+/// A grammar from the skill's console that matched the user's request
+pub struct Intent {
+    #[serde(default)]
+    pub slots: HashMap<String, Slot>,
+}
+
+impl Intent {
+    #[must_use]
+    pub fn slot(&self, name: &str) -> Option<&Slot> {
+        self.slots.get(name)
+    }
+}
+
+#[test]
+fn intents_deserialize() {
+    use super::{DateTime, Day, Nlu};
+    use serde_json::json;
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[serde(rename_all = "snake_case")]
+    enum Weekday {
+        Monday,
+        Tuesday,
+    }
+
+    let json = json!({
+        "tokens": ["где", "сидит", "иванов", "в", "понедельник", "завтра"],
+        "entities": [],
+        "intents": {
+            "teacher.where": {
+                "slots": {
+                    "teacher": {
+                        "type": "YANDEX.STRING",
+                        "tokens": { "start": 2, "end": 3 },
+                        "value": "иванов"
+                    },
+                    "weekday": {
+                        "type": "Weekday",
+                        "tokens": { "start": 3, "end": 5 },
+                        "value": "monday"
+                    },
+                    "when": {
+                        "type": "YANDEX.DATETIME",
+                        "tokens": { "start": 5, "end": 6 },
+                        "value": { "day": 1, "day_is_relative": true }
+                    }
+                }
+            },
+            "YANDEX.HELP": {}
+        }
+    });
+    let nlu: Nlu = serde_json::from_value(json).unwrap();
+    assert_eq!(nlu.intents.len(), 2);
+    assert_eq!(nlu.intents["YANDEX.HELP"], Intent::default());
+
+    let intent = &nlu.intents["teacher.where"];
+    assert_eq!(
+        intent.slot("teacher").map(|slot| &slot.value),
+        Some(&SlotValue::String {
+            slot_type: StringType::String,
+            value: String::from("иванов")
+        })
+    );
+    assert_eq!(
+        intent.slot("weekday").and_then(Slot::custom),
+        Some(Weekday::Monday)
+    );
+    assert_eq!(
+        intent.slot("when").and_then(Slot::entity),
+        Some(&YandexEnteties::DateTime(DateTime {
+            day: Some(Day {
+                day: 1.into(),
+                day_is_relative: true
+            }),
+            ..Default::default()
+        }))
+    );
+    assert_eq!(intent.slot("weekday").and_then(Slot::entity), None);
+    assert_eq!(intent.slot("room"), None);
+}
@@ -0,0 +1,235 @@
+use super::Interfaces;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A button shown under the response
+pub struct Button {
+    /// Text on the button, max 64 characters
+    pub title: String,
+    /// Arbitrary JSON, which is sent back in [`super::Request`] when the button is pressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+    /// URL, opened when the button is pressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// `true` hides the button after the next user's message, turning it into a suggest
+    #[serde(default)]
+    pub hide: bool,
+}
+
+impl Button {
+    /// A suggest that sends `payload` back in [`super::Request::payload`] when pressed
+    pub fn with_payload(title: impl Into<String>, payload: &impl Serialize) -> Self {
+        Self {
+            title: title.into(),
+            payload: serde_json::to_value(payload).ok(),
+            url: None,
+            hide: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CardHeader {
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CardItem {
+    /// Identifier of an image uploaded to the skill's storage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Button, pressed when the item is clicked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<Button>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CardFooter {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<Button>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+/// A card shown instead of the response text on the devices with a screen
+pub enum Card {
+    /// A single image
+    BigImage {
+        image_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+    },
+    /// A list of up to 5 items
+    ItemsList {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        header: Option<CardHeader>,
+        items: Vec<CardItem>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        footer: Option<CardFooter>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Describes the content of an answer to [`super::InputType::ShowPull`]
+pub struct ShowItemMeta {
+    /// Identifier of the content, the same content isn't played twice
+    pub content_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_tts: Option<String>,
+    /// When the content was published, in RFC 3339
+    pub publication_date: String,
+    /// When the content gets outdated, in RFC 3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+/// What Alice says and shows to the user
+pub struct Response {
+    /// Text shown to the user, max 1024 characters
+    pub text: String,
+    /// Text spoken to the user, max 1024 characters. [`Response::text`] is spoken if it is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<Card>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<Button>,
+    /// Required in the answers to the shows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_item_meta: Option<ShowItemMeta>,
+    /// `true` ends the conversation with the skill
+    pub end_session: bool,
+}
+
+impl Response {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Drops what the device can't show, so that a speaker only says the answer
+    pub fn fit(&mut self, interfaces: &Interfaces) {
+        if !interfaces.has_screen() {
+            self.card = None;
+            self.buttons.clear();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Everything the skill's webhook sends back to Dialogs
+pub struct WebhookResponse {
+    pub response: Response,
+    /// Replaces the session state, which comes back in [`super::State::session`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_state: Option<Value>,
+    /// Updates the user state, which comes back in [`super::State::user`].
+    /// Properties set to `null` are removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_state_update: Option<Value>,
+    /// Replaces the application state, which comes back in [`super::State::application`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub application_state: Option<Value>,
+    /// Protocol version, currently `1.0`
+    pub version: String,
+}
+
+impl WebhookResponse {
+    #[must_use]
+    pub fn new(response: Response) -> Self {
+        Self {
+            response,
+            session_state: None,
+            user_state_update: None,
+            application_state: None,
+            version: String::from("1.0"),
+        }
+    }
+}
+
+#[test]
+fn response_serializes() {
+    use serde_json::json;
+    let response = Response {
+        text: String::from("Первая пара в 9:00"),
+        tts: Some(String::from("Первая пара в девять ноль ноль")),
+        card: Some(Card::ItemsList {
+            header: Some(CardHeader {
+                text: String::from("Понедельник"),
+            }),
+            items: vec![CardItem {
+                image_id: None,
+                title: String::from("9:00 Математика"),
+                description: Some(String::from("Б-436")),
+                button: None,
+            }],
+            footer: None,
+        }),
+        buttons: vec![Button {
+            title: String::from("Завтра"),
+            payload: None,
+            url: None,
+            hide: true,
+        }],
+        show_item_meta: None,
+        end_session: false,
+    };
+    let mut webhook_response = WebhookResponse::new(response);
+    webhook_response.session_state = Some(json!({ "value": 10 }));
+    assert_eq!(
+        serde_json::to_value(&webhook_response).unwrap(),
+        json!({
+            "response": {
+                "text": "Первая пара в 9:00",
+                "tts": "Первая пара в девять ноль ноль",
+                "card": {
+                    "type": "ItemsList",
+                    "header": { "text": "Понедельник" },
+                    "items": [{ "title": "9:00 Математика", "description": "Б-436" }]
+                },
+                "buttons": [{ "title": "Завтра", "hide": true }],
+                "end_session": false
+            },
+            "session_state": { "value": 10 },
+            "version": "1.0"
+        })
+    );
+}
+
+#[test]
+fn response_fits_speakers() {
+    use serde_json::json;
+    let response = Response {
+        card: Some(Card::BigImage {
+            image_id: String::from("1027858/46aa9de1c1e6b2e3ae29"),
+            title: None,
+            description: None,
+        }),
+        buttons: vec![Button::with_payload("Завтра", &json!({}))],
+        ..Response::new("Первая пара в 9:00")
+    };
+
+    let mut on_phone = response.clone();
+    on_phone.fit(&Interfaces {
+        screen: Some(json!({})),
+        ..Interfaces::default()
+    });
+    assert_eq!(on_phone, response);
+
+    let mut on_speaker = response;
+    on_speaker.fit(&Interfaces::default());
+    assert_eq!(on_speaker, Response::new("Первая пара в 9:00"));
+}
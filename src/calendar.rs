@@ -0,0 +1,118 @@
+use crate::{
+    store::{Slot, WeekParity},
+    Class, Week,
+};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, Utc};
+
+/// Moscow doesn't observe daylight saving time, so it's always UTC+3
+pub fn moscow() -> FixedOffset {
+    FixedOffset::east_opt(3 * 3600).unwrap()
+}
+
+/// Current time in Moscow, where all the classes take place
+pub fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&moscow())
+}
+
+/// Start and end of every lesson, as hours and minutes
+const LESSON_TIMES: [((u32, u32), (u32, u32)); 7] = [
+    ((9, 0), (10, 35)),
+    ((10, 50), (12, 25)),
+    ((12, 40), (14, 15)),
+    ((14, 30), (16, 5)),
+    ((16, 20), (17, 55)),
+    ((18, 0), (19, 35)),
+    ((19, 45), (21, 20)),
+];
+
+/// Time the lesson starts at, first lesson is 0
+pub fn lesson_start(lesson: u8) -> NaiveTime {
+    let (hour, minute) = LESSON_TIMES[lesson as usize].0;
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// Time the lesson ends at, first lesson is 0
+pub fn lesson_end(lesson: u8) -> NaiveTime {
+    let (hour, minute) = LESSON_TIMES[lesson as usize].1;
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// The lesson that goes on at the time, or the next one during a break.
+/// [`None`] after the last lesson
+pub fn lesson_at(time: NaiveTime) -> Option<u8> {
+    (0..LESSON_TIMES.len() as u8).find(|lesson| lesson_end(*lesson) > time)
+}
+
+/// Formats a time the way it is written in the schedule, e.g. `9:00`
+pub fn format_time(time: NaiveTime) -> String {
+    time.format("%-H:%M").to_string()
+}
+
+/// The 1st of September of the academic year the `date` belongs to
+pub fn academic_year_start(date: NaiveDate) -> NaiveDate {
+    let year = if date.month() >= 9 {
+        date.year()
+    } else {
+        date.year() - 1
+    };
+    NaiveDate::from_ymd_opt(year, 9, 1).unwrap()
+}
+
+/// The week that contains the `semester_start` is an upper one, then they alternate
+pub fn week_parity(semester_start: NaiveDate, date: NaiveDate) -> WeekParity {
+    let monday =
+        |date: NaiveDate| date - chrono::Days::new(date.weekday().num_days_from_monday().into());
+    let weeks = (monday(date) - monday(semester_start)).num_weeks();
+    if weeks.rem_euclid(2) == 0 {
+        WeekParity::Upper
+    } else {
+        WeekParity::Lower
+    }
+}
+
+/// Classes of a week that take place on a date, ordered by their lessons
+pub fn day_classes(
+    week: &Week,
+    semester_start: NaiveDate,
+    date: NaiveDate,
+) -> impl Iterator<Item = (Slot, &Class)> {
+    let day = date.weekday().num_days_from_monday() as u8;
+    let parity = week_parity(semester_start, date);
+    let classes = &week[day as usize];
+    let classes = match parity {
+        WeekParity::Upper => &classes.upper_classes,
+        WeekParity::Lower => &classes.lower_classes,
+    };
+    classes
+        .iter()
+        .enumerate()
+        .filter_map(move |(lesson, class)| {
+            let slot = Slot {
+                day,
+                lesson: lesson as u8,
+                week: parity,
+            };
+            class.as_ref().map(|class| (slot, class))
+        })
+}
+
+#[test]
+fn weeks_alternate() {
+    let start = NaiveDate::from_ymd_opt(2022, 9, 1).unwrap();
+    let date = |month, day| NaiveDate::from_ymd_opt(2022, month, day).unwrap();
+    // 1st of September is Thursday, so the whole week from Monday is upper
+    assert_eq!(week_parity(start, date(8, 29)), WeekParity::Upper);
+    assert_eq!(week_parity(start, date(9, 4)), WeekParity::Upper);
+    assert_eq!(week_parity(start, date(9, 5)), WeekParity::Lower);
+    assert_eq!(week_parity(start, date(9, 12)), WeekParity::Upper);
+    assert_eq!(
+        academic_year_start(date(3, 1)),
+        NaiveDate::from_ymd_opt(2021, 9, 1).unwrap()
+    );
+    assert_eq!(format_time(lesson_start(0)), "9:00");
+    let time = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+    assert_eq!(lesson_at(time(8, 0)), Some(0));
+    assert_eq!(lesson_at(time(10, 40)), Some(1));
+    assert_eq!(lesson_at(time(12, 0)), Some(1));
+    assert_eq!(lesson_at(time(21, 20)), None);
+}
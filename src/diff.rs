@@ -0,0 +1,294 @@
+use crate::{
+    store::{week_classes, Schedules, Slot},
+    Class, Course, Week,
+};
+use serde::{Deserialize, Serialize};
+
+/// A single difference between two versions of a subgroup's week
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Added {
+        slot: Slot,
+        class: Class,
+    },
+    Removed {
+        slot: Slot,
+        class: Class,
+    },
+    /// The same class was moved into another slot or room, possibly with another teacher
+    Moved {
+        from: Slot,
+        to: Slot,
+        before: Class,
+        after: Class,
+    },
+    /// The same class in the same slot and room now has another teacher
+    Restaffed {
+        slot: Slot,
+        before: Class,
+        after: Class,
+    },
+}
+
+/// All the changes of a single group or subgroup
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct GroupChanges {
+    pub institute: String,
+    pub course: String,
+    pub group: String,
+    /// [`None`] if the group isn't divided into subgroups
+    pub subgroup: Option<u8>,
+    pub changes: Vec<Change>,
+}
+
+/// Classes are considered the same if they have the same subject and type
+fn same_class(before: &Class, after: &Class) -> bool {
+    before.name == after.name && before.class_type == after.class_type
+}
+
+/// Compares two versions of a week
+pub fn diff_weeks(before: Option<&Week>, after: Option<&Week>) -> Vec<Change> {
+    let mut removed: Vec<(Slot, &Class)> = before.into_iter().flat_map(week_classes).collect();
+    let mut added: Vec<(Slot, &Class)> = after.into_iter().flat_map(week_classes).collect();
+
+    // Unchanged classes
+    removed.retain(|old| match added.iter().position(|new| new == old) {
+        Some(position) => {
+            added.remove(position);
+            false
+        }
+        None => true,
+    });
+
+    let mut changes = Vec::new();
+
+    // Classes in the same slot with a new room or teacher
+    removed.retain(|(slot, old)| {
+        let position = added
+            .iter()
+            .position(|(new_slot, new)| new_slot == slot && same_class(old, new));
+        let Some(position) = position else {
+            return true;
+        };
+        let (_, new) = added.remove(position);
+        changes.push(if old.room == new.room {
+            Change::Restaffed {
+                slot: *slot,
+                before: (*old).clone(),
+                after: new.clone(),
+            }
+        } else {
+            Change::Moved {
+                from: *slot,
+                to: *slot,
+                before: (*old).clone(),
+                after: new.clone(),
+            }
+        });
+        false
+    });
+
+    // Classes that were moved to another slot, preferring the ones that kept their teacher
+    removed.retain(|(slot, old)| {
+        let position = added
+            .iter()
+            .position(|(_, new)| same_class(old, new) && old.teacher == new.teacher)
+            .or_else(|| added.iter().position(|(_, new)| same_class(old, new)));
+        let Some(position) = position else {
+            return true;
+        };
+        let (new_slot, new) = added.remove(position);
+        changes.push(Change::Moved {
+            from: *slot,
+            to: new_slot,
+            before: (*old).clone(),
+            after: new.clone(),
+        });
+        false
+    });
+
+    changes.extend(removed.into_iter().map(|(slot, class)| Change::Removed {
+        slot,
+        class: class.clone(),
+    }));
+    changes.extend(added.into_iter().map(|(slot, class)| Change::Added {
+        slot,
+        class: class.clone(),
+    }));
+    changes
+}
+
+/// Compares two versions of a workbook, matching the groups by their course, name and subgroup
+pub fn diff_courses(institute: &str, before: &[Course], after: &[Course]) -> Vec<GroupChanges> {
+    type Key<'a> = (&'a str, &'a str, Option<u8>);
+    fn weeks(courses: &[Course]) -> Vec<(Key<'_>, &Week)> {
+        courses
+            .iter()
+            .flat_map(|course| {
+                course.groups.iter().flat_map(move |group| {
+                    group.weeks().into_iter().map(move |(subgroup, week)| {
+                        ((course.name.as_str(), group.name.as_str(), subgroup), week)
+                    })
+                })
+            })
+            .collect()
+    }
+    fn find<'a>(weeks: &[(Key, &'a Week)], key: Key) -> Option<&'a Week> {
+        weeks
+            .iter()
+            .find(|(other, _)| *other == key)
+            .map(|(_, week)| *week)
+    }
+    let before = weeks(before);
+    let after = weeks(after);
+
+    // Groups of the new version first, then the ones that were removed
+    let keys = after.iter().map(|(key, _)| *key).chain(
+        before
+            .iter()
+            .map(|(key, _)| *key)
+            .filter(|key| find(&after, *key).is_none()),
+    );
+
+    keys.filter_map(|key @ (course, group, subgroup)| {
+        let changes = diff_weeks(find(&before, key), find(&after, key));
+        (!changes.is_empty()).then(|| GroupChanges {
+            institute: institute.to_string(),
+            course: course.to_string(),
+            group: group.to_string(),
+            subgroup,
+            changes,
+        })
+    })
+    .collect()
+}
+
+/// Compares the workbooks present in both versions of the schedules.
+/// A workbook missing in one of them has no version to compare with, not an empty one
+pub fn diff_schedules(before: &Schedules, after: &Schedules) -> Vec<GroupChanges> {
+    let mut changes = Vec::new();
+    for (institute, courses) in after.institutes() {
+        if let Some(old) = before.institute(institute) {
+            changes.extend(diff_courses(institute, old, courses));
+        }
+    }
+    changes
+}
+
+#[test]
+fn diff_detects_changes() {
+    use crate::{store::set_class, store::WeekParity, ClassType};
+    use std::sync::Arc;
+
+    let class = |name: &str, teacher: &str, room: &str| Class {
+        name: Arc::from(name),
+        class_type: ClassType::Lection,
+        teacher: Some(Arc::from(teacher)),
+        room: Arc::from(room),
+    };
+    let slot = |day, lesson| Slot {
+        day,
+        lesson,
+        week: WeekParity::Upper,
+    };
+
+    let mut before = Week::default();
+    set_class(&mut before, slot(0, 0), class("Math", "Ivanov", "Б-436"));
+    set_class(&mut before, slot(0, 1), class("Physics", "Petrov", "Л-550"));
+    set_class(
+        &mut before,
+        slot(1, 0),
+        class("History", "Sidorov", "А-305"),
+    );
+    set_class(
+        &mut before,
+        slot(2, 0),
+        class("Chemistry", "Smirnov", "Б-101"),
+    );
+    set_class(&mut before, slot(3, 0), class("English", "Brown", "Г-201"));
+
+    let mut after = Week::default();
+    set_class(&mut after, slot(0, 0), class("Math", "Ivanov", "Б-436"));
+    set_class(
+        &mut after,
+        slot(0, 1),
+        class("Physics", "Kuznetsov", "Л-550"),
+    );
+    set_class(&mut after, slot(1, 0), class("History", "Sidorov", "А-307"));
+    set_class(
+        &mut after,
+        slot(2, 3),
+        class("Chemistry", "Smirnov", "Б-101"),
+    );
+    set_class(&mut after, slot(4, 0), class("Drawing", "Green", "К-1"));
+
+    let changes = diff_weeks(Some(&before), Some(&after));
+    assert_eq!(
+        changes,
+        [
+            Change::Restaffed {
+                slot: slot(0, 1),
+                before: class("Physics", "Petrov", "Л-550"),
+                after: class("Physics", "Kuznetsov", "Л-550"),
+            },
+            Change::Moved {
+                from: slot(1, 0),
+                to: slot(1, 0),
+                before: class("History", "Sidorov", "А-305"),
+                after: class("History", "Sidorov", "А-307"),
+            },
+            Change::Moved {
+                from: slot(2, 0),
+                to: slot(2, 3),
+                before: class("Chemistry", "Smirnov", "Б-101"),
+                after: class("Chemistry", "Smirnov", "Б-101"),
+            },
+            Change::Removed {
+                slot: slot(3, 0),
+                class: class("English", "Brown", "Г-201"),
+            },
+            Change::Added {
+                slot: slot(4, 0),
+                class: class("Drawing", "Green", "К-1"),
+            },
+        ]
+    );
+    assert!(diff_weeks(Some(&before), Some(&before)).is_empty());
+}
+
+#[test]
+fn diff_skips_workbooks_without_a_version() {
+    use crate::{store::set_class, store::WeekParity, ClassType, Course, GroupInfo};
+    use std::sync::Arc;
+
+    let mut week = Week::default();
+    let slot = Slot {
+        day: 0,
+        lesson: 0,
+        week: WeekParity::Upper,
+    };
+    let class = Class {
+        name: Arc::from("Math"),
+        class_type: ClassType::Lection,
+        teacher: None,
+        room: Arc::from("Б-436"),
+    };
+    set_class(&mut week, slot, class);
+    let courses = |week: Week| {
+        let group = GroupInfo::from_weeks(String::from("БИВТ-21-15"), vec![(None, week)]);
+        vec![Course::new(String::from("2 курс"), vec![group])]
+    };
+
+    let mut before = Schedules::default();
+    before.insert(String::from("itkn"), courses(Week::default()));
+    let mut after = Schedules::default();
+    after.insert(String::from("itkn"), courses(week.clone()));
+    // Wasn't parsed yet at the time of the first version
+    after.insert(String::from("ibo"), courses(week));
+
+    let changes = diff_schedules(&before, &after);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].institute, "itkn");
+    assert!(diff_schedules(&Schedules::default(), &after).is_empty());
+}
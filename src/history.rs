@@ -0,0 +1,109 @@
+use crate::{store::Schedules, Course};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Every parsed version of a workbook is kept in `<history dir>/<workbook>/<unix time>.json`
+fn institute_dir(history_dir: &Path, institute: &str) -> PathBuf {
+    history_dir.join(institute)
+}
+
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Saves a freshly parsed version of a workbook
+pub fn record(
+    history_dir: &Path,
+    institute: &str,
+    courses: &[Course],
+    parsed_at: SystemTime,
+) -> io::Result<()> {
+    let dir = institute_dir(history_dir, institute);
+    std::fs::create_dir_all(&dir)?;
+    let mut file = BufWriter::new(File::create(
+        dir.join(unix_time(parsed_at).to_string())
+            .with_extension("json"),
+    )?);
+    serde_json::to_writer(&mut file, courses)?;
+    file.flush()
+}
+
+/// Unix times of all the recorded versions of a workbook, from the oldest to the newest
+pub fn versions(history_dir: &Path, institute: &str) -> io::Result<Vec<u64>> {
+    let dir = institute_dir(history_dir, institute);
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut versions = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(time) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str()?.parse().ok())
+            {
+                versions.push(time);
+            }
+        }
+    }
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+/// The version of a workbook that was current at the `time`
+pub fn load_at(history_dir: &Path, institute: &str, time: u64) -> io::Result<Option<Vec<Course>>> {
+    let versions = versions(history_dir, institute)?;
+    let Some(version) = versions.into_iter().rev().find(|version| *version <= time) else {
+        return Ok(None);
+    };
+    let path = institute_dir(history_dir, institute)
+        .join(version.to_string())
+        .with_extension("json");
+    let file = File::open(path)?;
+    Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+}
+
+/// Versions of all the `institutes` that were current at the `time`
+/// Workbooks that weren't parsed yet at that time are left out, [`crate::diff::diff_schedules`] skips them
+pub fn schedules_at<'a>(
+    history_dir: &Path,
+    institutes: impl IntoIterator<Item = &'a str>,
+    time: u64,
+) -> io::Result<Schedules> {
+    let mut schedules = Schedules::default();
+    for institute in institutes {
+        if let Some(courses) = load_at(history_dir, institute, time)? {
+            schedules.insert(institute.to_string(), courses);
+        }
+    }
+    schedules.intern();
+    Ok(schedules)
+}
+
+#[test]
+fn history_keeps_versions() {
+    use std::time::Duration;
+    let dir = std::env::temp_dir().join(format!("misisa-history-{}", std::process::id()));
+    let first = UNIX_EPOCH + Duration::from_secs(1_000);
+    let second = UNIX_EPOCH + Duration::from_secs(2_000);
+    let old = vec![Course::new(String::from("Old"), Vec::new())];
+    let new = vec![Course::new(String::from("New"), Vec::new())];
+    record(&dir, "itkn", &old, first).unwrap();
+    record(&dir, "itkn", &new, second).unwrap();
+
+    assert_eq!(versions(&dir, "itkn").unwrap(), [1_000, 2_000]);
+    assert_eq!(load_at(&dir, "itkn", 999).unwrap(), None);
+    assert_eq!(load_at(&dir, "itkn", 1_500).unwrap(), Some(old));
+    assert_eq!(load_at(&dir, "itkn", 2_000).unwrap(), Some(new));
+    assert_eq!(load_at(&dir, "other", 2_000).unwrap(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,278 @@
+use crate::{
+    store::{set_class, week_classes, Schedules, Slot},
+    Class, ClassType, Course, GroupInfo, Week,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Deduplicates strings, so the equal ones share a single allocation
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// Shared copy of the `string`, allocated only the first time it's seen
+    pub fn intern_str(&mut self, string: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(string);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    pub fn intern(&mut self, string: &mut Arc<str>) {
+        match self.strings.get(string) {
+            Some(interned) => *string = interned.clone(),
+            None => {
+                self.strings.insert(string.clone());
+            }
+        }
+    }
+
+    pub fn intern_class(&mut self, class: &mut Class) {
+        self.intern(&mut class.name);
+        if let Some(teacher) = &mut class.teacher {
+            self.intern(teacher);
+        }
+        self.intern(&mut class.room);
+    }
+}
+
+/// Strings, referenced by their position in the table
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+#[serde(transparent)]
+pub struct SymbolTable {
+    symbols: Vec<Arc<str>>,
+    #[serde(skip)]
+    ids: HashMap<Arc<str>, u32>,
+}
+
+impl SymbolTable {
+    pub fn id(&mut self, symbol: &Arc<str>) -> u32 {
+        if let Some(id) = self.ids.get(symbol) {
+            return *id;
+        }
+        let id = self.symbols.len() as u32;
+        self.symbols.push(symbol.clone());
+        self.ids.insert(symbol.clone(), id);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Arc<str>> {
+        self.symbols.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+/// A class that references its subject, teacher and room by their ids in the symbol tables
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NormalisedClass {
+    slot: Slot,
+    subject: u32,
+    class_type: ClassType,
+    teacher: Option<u32>,
+    room: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NormalisedSubgroup {
+    /// [`None`] if the group isn't divided into subgroups
+    number: Option<u8>,
+    classes: Vec<NormalisedClass>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NormalisedGroup {
+    name: String,
+    subgroups: Vec<NormalisedSubgroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NormalisedCourse {
+    name: String,
+    groups: Vec<NormalisedGroup>,
+}
+
+/// All the schedules with subjects, teachers and rooms moved into shared symbol tables
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+pub struct NormalisedSchedules {
+    subjects: SymbolTable,
+    teachers: SymbolTable,
+    rooms: SymbolTable,
+    institutes: BTreeMap<String, Vec<NormalisedCourse>>,
+}
+
+impl NormalisedSchedules {
+    pub fn new(schedules: &Schedules) -> Self {
+        let mut normalised = Self::default();
+        for (institute, courses) in schedules.institutes() {
+            let courses = courses
+                .iter()
+                .map(|course| NormalisedCourse {
+                    name: course.name.clone(),
+                    groups: course
+                        .groups
+                        .iter()
+                        .map(|group| normalised.normalise_group(group))
+                        .collect(),
+                })
+                .collect();
+            normalised.institutes.insert(institute.to_string(), courses);
+        }
+        normalised
+    }
+
+    fn normalise_group(&mut self, group: &GroupInfo) -> NormalisedGroup {
+        let subgroups = group
+            .weeks()
+            .into_iter()
+            .map(|(number, week)| NormalisedSubgroup {
+                number,
+                classes: week_classes(week)
+                    .map(|(slot, class)| NormalisedClass {
+                        slot,
+                        subject: self.subjects.id(&class.name),
+                        class_type: class.class_type.clone(),
+                        teacher: class
+                            .teacher
+                            .as_ref()
+                            .map(|teacher| self.teachers.id(teacher)),
+                        room: self.rooms.id(&class.room),
+                    })
+                    .collect(),
+            })
+            .collect();
+        NormalisedGroup {
+            name: group.name.clone(),
+            subgroups,
+        }
+    }
+
+    fn denormalise_class(&self, class: &NormalisedClass) -> Option<Class> {
+        let teacher = match class.teacher {
+            Some(teacher) => Some(self.teachers.get(teacher)?.clone()),
+            None => None,
+        };
+        Some(Class {
+            name: self.subjects.get(class.subject)?.clone(),
+            class_type: class.class_type.clone(),
+            teacher,
+            room: self.rooms.get(class.room)?.clone(),
+        })
+    }
+
+    /// Restores the schedules, sharing the strings between the classes
+    /// Returns [`None`] if a class references a symbol that isn't in the tables
+    pub fn to_schedules(&self) -> Option<Schedules> {
+        let mut schedules = Schedules::default();
+        for (institute, courses) in &self.institutes {
+            let mut restored = Vec::with_capacity(courses.len());
+            for course in courses {
+                let mut groups = Vec::with_capacity(course.groups.len());
+                for group in &course.groups {
+                    let mut weeks = Vec::with_capacity(group.subgroups.len());
+                    for subgroup in &group.subgroups {
+                        let mut week = Week::default();
+                        for class in &subgroup.classes {
+                            set_class(&mut week, class.slot, self.denormalise_class(class)?);
+                        }
+                        weeks.push((subgroup.number, week));
+                    }
+                    groups.push(GroupInfo::from_weeks(group.name.clone(), weeks));
+                }
+                restored.push(Course::new(course.name.clone(), groups));
+            }
+            schedules.insert(institute.clone(), restored);
+        }
+        Some(schedules)
+    }
+}
+
+/// Memory and size measurements of a schedule, with and without interning
+#[derive(Debug, Serialize)]
+pub struct Measurements {
+    pub classes: usize,
+    pub subjects: usize,
+    pub teachers: usize,
+    pub rooms: usize,
+    /// Bytes taken by the names, teachers and rooms when each class owns its own strings
+    pub string_bytes: usize,
+    /// Bytes taken by the same strings when they are interned
+    pub interned_string_bytes: usize,
+    pub json_bytes: usize,
+    pub normalised_json_bytes: usize,
+}
+
+impl Measurements {
+    pub fn new(schedules: &Schedules) -> Self {
+        let mut string_bytes = 0;
+        let mut unique: HashSet<&str> = HashSet::new();
+        let mut classes = 0;
+        for class in schedules.classes() {
+            classes += 1;
+            let class = class.class;
+            for string in [Some(&class.name), class.teacher.as_ref(), Some(&class.room)]
+                .into_iter()
+                .flatten()
+            {
+                string_bytes += string.len();
+                unique.insert(string);
+            }
+        }
+        let normalised = NormalisedSchedules::new(schedules);
+        let json_bytes = schedules
+            .institutes()
+            .map(|(_, courses)| serde_json::to_vec(courses).unwrap().len())
+            .sum();
+        Self {
+            classes,
+            subjects: normalised.subjects.len(),
+            teachers: normalised.teachers.len(),
+            rooms: normalised.rooms.len(),
+            string_bytes,
+            interned_string_bytes: unique.iter().map(|string| string.len()).sum(),
+            json_bytes,
+            normalised_json_bytes: serde_json::to_vec(&normalised).unwrap().len(),
+        }
+    }
+}
+
+#[test]
+fn normalised_round_trip() {
+    use calamine::{open_workbook, Xlsx};
+    let mut excel: Xlsx<_> = open_workbook("test/Test.xlsx").unwrap();
+    let parsed = crate::ExcelData::new(&mut excel).parse();
+    let mut schedules = Schedules::default();
+    schedules.insert(String::from("first"), parsed.to_vec());
+    schedules.insert(String::from("second"), parsed.to_vec());
+
+    let normalised = NormalisedSchedules::new(&schedules);
+    let json = serde_json::to_string(&normalised).unwrap();
+    let deserialized: NormalisedSchedules = serde_json::from_str(&json).unwrap();
+    let restored = deserialized.to_schedules().unwrap();
+    assert_eq!(restored, schedules);
+
+    // Both institutes share the same strings
+    let mut classes = restored
+        .classes()
+        .filter(|class| &*class.class.name == "Math");
+    let first = classes.next().unwrap();
+    let second = classes.find(|class| class.institute == "second").unwrap();
+    assert!(Arc::ptr_eq(&first.class.name, &second.class.name));
+
+    let measurements = Measurements::new(&schedules);
+    assert!(measurements.interned_string_bytes < measurements.string_bytes);
+    assert!(measurements.normalised_json_bytes < measurements.json_bytes);
+}
@@ -0,0 +1,736 @@
+use calamine::{open_workbook, open_workbook_auto, DataType, Range, Reader, Xls, Xlsx};
+use interner::Interner;
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    env,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    mem::MaybeUninit,
+    path::Path,
+    sync::Arc,
+    time::SystemTime,
+};
+use store::Backend;
+
+pub mod alisa;
+pub mod calendar;
+pub mod diff;
+pub mod history;
+pub mod interner;
+pub mod marusia;
+pub mod skill;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
+pub mod telegram;
+pub mod webhooks;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum ClassType {
+    Lection,
+    Practice,
+    Lab,
+    Unknown(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Class {
+    name: Arc<str>,
+    class_type: ClassType,
+    teacher: Option<Arc<str>>,
+    room: Arc<str>,
+}
+
+impl Class {
+    fn new(name_and_teacher: &DataType, room: &DataType, interner: &mut Interner) -> Option<Self> {
+        // Name and teacher in the first is placed in this way:
+        // Name (Type)
+        // Teacher?
+        // The room is placed in a second cell
+
+        let name_and_teacher = match name_and_teacher {
+            DataType::String(s) => s,
+            _ => return None,
+        };
+
+        let (name, class_type) = match name_and_teacher.split_once(" (") {
+            Some((name, class_type)) => (name, class_type),
+            None => return None,
+        };
+
+        let (class_type, mut teacher) = match class_type.split_once('\n') {
+            Some((class, teacher)) => (class, Some(teacher)),
+            None => (class_type, None),
+        };
+
+        if teacher.map(|teach| teach.is_empty()).unwrap_or_default() {
+            teacher = None;
+        }
+
+        let class_type = match class_type.strip_suffix(')') {
+            Some(class_type) => class_type,
+            None => return None,
+        };
+
+        let class_type = match class_type {
+            "Лекционные" => ClassType::Lection,
+            "Практические" => ClassType::Practice,
+            "Лабораторные" => ClassType::Lab,
+            _ => ClassType::Unknown(class_type.to_string()),
+        };
+
+        let room = match room {
+            DataType::String(s) => s,
+            _ => return None,
+        };
+
+        Some(Self {
+            name: interner.intern_str(name),
+            class_type,
+            teacher: teacher.map(|teacher| interner.intern_str(teacher)),
+            room: interner.intern_str(room),
+        })
+    }
+}
+
+pub type Week = Box<[Day; 7]>;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Subgroup {
+    number: u8,
+    days: Week,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+pub struct Day {
+    upper_classes: [Option<Class>; 7],
+    lower_classes: [Option<Class>; 7],
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum WeekInfo {
+    WithSubgroups(Vec<Subgroup>),
+    WithoutSubgroup(Week),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct GroupInfo {
+    name: String,
+    subgroups: WeekInfo,
+}
+
+impl GroupInfo {
+    pub fn get_subgroup(&self, subgroup_number: u8) -> Option<&Subgroup> {
+        match &self.subgroups {
+            WeekInfo::WithSubgroups(subgroups) => subgroups
+                .iter()
+                .find(|subgroup| subgroup.number == subgroup_number),
+            WeekInfo::WithoutSubgroup(_) => None,
+        }
+    }
+
+    /// Weeks of all the subgroups of this group
+    /// A group without subgroups has a single week without a number
+    fn weeks(&self) -> Vec<(Option<u8>, &Week)> {
+        match &self.subgroups {
+            WeekInfo::WithSubgroups(subgroups) => subgroups
+                .iter()
+                .map(|subgroup| (Some(subgroup.number), &subgroup.days))
+                .collect(),
+            WeekInfo::WithoutSubgroup(week) => vec![(None, week)],
+        }
+    }
+
+    /// Creates a group from the weeks of its subgroups
+    /// A single week without a number means that the group isn't divided into subgroups
+    fn from_weeks(name: String, mut weeks: Vec<(Option<u8>, Week)>) -> Self {
+        let subgroups = match weeks.as_slice() {
+            [(None, _)] => WeekInfo::WithoutSubgroup(weeks.pop().unwrap().1),
+            _ => WeekInfo::WithSubgroups(
+                weeks
+                    .into_iter()
+                    .map(|(number, days)| Subgroup {
+                        number: number.unwrap_or_default(),
+                        days,
+                    })
+                    .collect(),
+            ),
+        };
+        Self { name, subgroups }
+    }
+
+    fn weeks_mut(&mut self) -> Vec<&mut Week> {
+        match &mut self.subgroups {
+            WeekInfo::WithSubgroups(subgroups) => subgroups
+                .iter_mut()
+                .map(|subgroup| &mut subgroup.days)
+                .collect(),
+            WeekInfo::WithoutSubgroup(week) => vec![week],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Course {
+    name: String,
+    groups: Vec<GroupInfo>,
+}
+
+impl Course {
+    pub fn new(name: String, groups: Vec<GroupInfo>) -> Self {
+        Self { name, groups }
+    }
+
+    pub fn find_group(&self, group_name: &str) -> Option<&GroupInfo> {
+        self.groups.iter().find(|group| group.name == group_name)
+    }
+}
+
+pub struct ExcelData {
+    pages: [(String, Range<DataType>); 4],
+}
+
+/// Hex encoded SHA-256 of the contents of a file
+fn content_hash(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn parse_schedules(backend: &Backend) -> std::io::Result<Vec<diff::GroupChanges>> {
+    // We have a dir for storing schedules
+    // That dir has a "parsed" subdir and a "raw" subdir
+    // For each file in the "raw" subdir we parse it and save it in the "parsed" subdir as json
+    // The file names, not including file extensions, should match
+    // The parsed schedule is also saved into the backend, keyed by the same file name
+    // Every parsed version is also kept in the "history" subdir, so the changes can be tracked
+    // The changes against the previously parsed version of each file are returned
+    // The SHA-256 of each parsed file is kept next to its json, so the unchanged files aren't parsed again
+    // The parsed files of the workbooks that were deleted from the "raw" subdir are removed
+    // After that all the parsed schedules are written into a single snapshot for faster startup
+
+    let raw_dir = Path::new("schedules").join("raw");
+    let parsed_dir = Path::new("schedules").join("parsed");
+    let history_dir = Path::new("schedules").join("history");
+    let snapshot_path = snapshot::path(&parsed_dir);
+    let mut changed = !snapshot_path.exists();
+    let mut changes = Vec::new();
+    let mut workbooks = HashSet::new();
+
+    for entry in std::fs::read_dir(&raw_dir)? {
+        let entry = entry?;
+        let file_path = entry.path();
+        assert!(file_path.is_file());
+        let file_name = file_path.file_name();
+
+        // Create a file with the same name as the original file
+        let parsed_file_name = file_name.unwrap().to_str().unwrap();
+        let parsed_file_path = parsed_dir.join(parsed_file_name).with_extension("json");
+        let institute = parsed_file_path.file_stem().unwrap().to_string_lossy();
+        workbooks.insert(institute.to_string());
+
+        // Skip the files that weren't changed since they were parsed
+        // Their content is compared, as a reupload or a copy changes the mtime of the same workbook
+        let hash_path = parsed_file_path.with_extension("sha256");
+        let hash = content_hash(&file_path)?;
+        let parsed_hash = std::fs::read_to_string(&hash_path).ok();
+        if parsed_file_path.exists()
+            && parsed_hash.as_deref() == Some(hash.as_str())
+            && backend.contains(&institute)?
+        {
+            continue;
+        }
+
+        let parsed = ExcelData::open(&file_path).parse();
+
+        if let Ok(previous_file) = File::open(&parsed_file_path) {
+            let previous: Vec<Course> = serde_json::from_reader(BufReader::new(previous_file))?;
+            changes.extend(diff::diff_courses(&institute, &previous, &parsed));
+        }
+
+        let mut parsed_file = BufWriter::new(File::create(&parsed_file_path)?);
+        serde_json::to_writer_pretty(&mut parsed_file, &parsed)?;
+        parsed_file.flush()?;
+        backend.save(&institute, &parsed)?;
+        history::record(&history_dir, &institute, &parsed, SystemTime::now())?;
+        std::fs::write(&hash_path, hash)?;
+        changed = true;
+    }
+
+    for entry in std::fs::read_dir(&parsed_dir)? {
+        let parsed_file_path = entry?.path();
+        if parsed_file_path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let institute = parsed_file_path.file_stem().unwrap().to_string_lossy();
+        if !workbooks.contains(institute.as_ref()) {
+            std::fs::remove_file(&parsed_file_path)?;
+            let _ = std::fs::remove_file(parsed_file_path.with_extension("sha256"));
+            backend.remove(&institute)?;
+            changed = true;
+        }
+    }
+
+    if changed {
+        let schedules = store::Schedules::load_dir(&parsed_dir)?;
+        let fingerprint = snapshot::fingerprint(&parsed_dir)?;
+        snapshot::write(&snapshot_path, &schedules, fingerprint)?;
+        // Optionally write all the schedules with subjects, teachers and rooms in shared tables
+        if env::var_os("MISISA_NORMALISED_JSON").is_some() {
+            let normalised = interner::NormalisedSchedules::new(&schedules);
+            let normalised_file = File::create(Path::new("schedules").join("normalised.json"))?;
+            serde_json::to_writer_pretty(normalised_file, &normalised)?;
+        }
+    }
+    Ok(changes)
+}
+
+#[test]
+fn test_excel_parsing() {
+    use calamine::{open_workbook, Xlsx};
+    let mut excel: Xlsx<_> = open_workbook("test/Test.xlsx").unwrap();
+    let excel_data = ExcelData::new(&mut excel);
+    let parsed = excel_data.parse();
+    println!("Parsed: {:?}", parsed);
+
+    let parsed_course = &parsed[0];
+    let parsed_group = &parsed_course.groups[0];
+    let (parsed_subgroup, second_parsed_subgroup) =
+        if let WeekInfo::WithSubgroups(subgroups) = &parsed_group.subgroups {
+            (&subgroups[0], &subgroups[1])
+        } else {
+            panic!("Expected subgroups, got {:?}", parsed_group.subgroups);
+        };
+    let parsed_day = &parsed_subgroup.days[0];
+    let parsed_upper_class = parsed_day.upper_classes[0]
+        .as_ref()
+        .expect("Expected a class");
+    let parsed_lower_class = second_parsed_subgroup.days[6].lower_classes[6]
+        .as_ref()
+        .expect("Expected a class");
+
+    let test_upper_class = Class {
+        name: Arc::from("Math"),
+        class_type: ClassType::Practice,
+        teacher: Some(Arc::from("Teacher")),
+        room: Arc::from("Class"),
+    };
+    let test_lower_class = Class {
+        name: Arc::from("CS"),
+        class_type: ClassType::Lab,
+        teacher: Some(Arc::from("Teacher2")),
+        room: Arc::from("Class2"),
+    };
+    let test_day = Day {
+        upper_classes: [
+            Some(test_upper_class.clone()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ],
+        lower_classes: Default::default(),
+    };
+    let test_second_day = Day {
+        upper_classes: Default::default(),
+        lower_classes: [
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(test_lower_class.clone()),
+        ],
+    };
+    let test_subgroup = Subgroup {
+        number: 1,
+        days: Box::new([
+            test_day.clone(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+        ]),
+    };
+    let test_second_subgroup = Subgroup {
+        number: 2,
+        days: Box::new([
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            Day::default(),
+            test_second_day,
+        ]),
+    };
+    let test_group = GroupInfo {
+        name: String::from("Group"),
+        subgroups: WeekInfo::WithSubgroups(vec![test_subgroup.clone(), test_second_subgroup]),
+    };
+    let test_course = Course {
+        name: String::from("Course"),
+        groups: vec![test_group.clone()],
+    };
+
+    assert_eq!(parsed_upper_class, &test_upper_class);
+    assert_eq!(parsed_lower_class, &test_lower_class);
+    assert_eq!(parsed_day, &test_day);
+    assert_eq!(parsed_subgroup, &test_subgroup);
+    assert_eq!(parsed_group, &test_group);
+    assert_eq!(parsed_course, &test_course);
+}
+
+#[test]
+fn parsed_classes_share_strings() {
+    let mut interner = Interner::default();
+    let cell = DataType::String("Math (Практические)\nTeacher".to_string());
+    let room = DataType::String("Class".to_string());
+    let first = Class::new(&cell, &room, &mut interner).unwrap();
+    let second = Class::new(&cell, &room, &mut interner).unwrap();
+    assert!(Arc::ptr_eq(&first.name, &second.name));
+    assert!(Arc::ptr_eq(
+        first.teacher.as_ref().unwrap(),
+        second.teacher.as_ref().unwrap()
+    ));
+    assert!(Arc::ptr_eq(&first.room, &second.room));
+}
+
+impl ExcelData {
+    pub fn open(file_path: &Path) -> Self {
+        match file_path.extension() {
+            Some(ext) if ext == "xlsx" => {
+                let mut excel_data: Xlsx<_> = open_workbook(file_path).unwrap();
+                Self::new(&mut excel_data)
+            }
+            Some(ext) if ext == "xls" => {
+                let mut excel_data: Xls<_> = open_workbook(file_path).unwrap();
+                Self::new(&mut excel_data)
+            }
+            _ => {
+                let mut excel_data = open_workbook_auto(file_path).unwrap();
+                Self::new(&mut excel_data)
+            }
+        }
+    }
+
+    pub fn new<T: std::io::Read + std::io::Seek>(sheets: &mut impl Reader<RS = T>) -> Self {
+        let pages = sheets.sheet_names();
+        assert_eq!(pages.len(), 4, "Excel file didn't have 4 pages");
+        let (first, second, third, fourth) = if let [first, second, third, fourth] = pages {
+            (first.clone(), second.clone(), third.clone(), fourth.clone())
+        } else {
+            unreachable!();
+        };
+        let info: [(String, Range<DataType>); 4] = [
+            (sheets.worksheet_range(&first).unwrap().unwrap(), first).swap(),
+            (sheets.worksheet_range(&second).unwrap().unwrap(), second).swap(),
+            (sheets.worksheet_range(&third).unwrap().unwrap(), third).swap(),
+            (sheets.worksheet_range(&fourth).unwrap().unwrap(), fourth).swap(),
+        ];
+        Self { pages: info }
+    }
+
+    pub fn parse(self) -> [Course; 4] {
+        let mut courses: [MaybeUninit<Course>; 4] = [
+            MaybeUninit::uninit(),
+            MaybeUninit::uninit(),
+            MaybeUninit::uninit(),
+            MaybeUninit::uninit(),
+        ];
+        let courses_iter = self.pages.into_par_iter().map(|(name, sheet)| {
+            let mut rows = sheet.rows();
+            // This is a row with group names
+            // We skip first 3 cells because info there doesn't matter
+            // The only cells that matter are the ones with strings in them, so we skip the rest
+            let first_row = rows
+                .next()
+                .unwrap()
+                .iter()
+                .skip(3)
+                .filter(|cell| cell.is_string());
+            // This is a row that contains info about subgroups
+            // We skip first 3 cells because info there doesn't matter, same as the first one
+            // Every second cell is guaranteed empty, so we skip it
+            let second_row = rows.next().unwrap().iter().skip(3).step_by(2);
+            // Capacity is 30, because in 2022 there were no more than 26 groups
+            let mut subgroups: Vec<Option<Vec<u8>>> = Vec::with_capacity(30);
+            /// Parses a cell into u8
+            /// # Panics
+            /// If contained data is not a string
+            fn parse_datacell(cell: &DataType) -> u8 {
+                cell.get_string().unwrap().parse().unwrap()
+            }
+            {
+                // This is a vector that can contain numbers of subgroups in a group
+                let mut subgroup_numbers: Option<Vec<u8>> = None;
+
+                for (cell_num, cell) in second_row.enumerate() {
+                    // If a cell is empty, it means that there is no subgroups in this group
+                    // This means that we finished getting previous group's subgroups
+                    // So we push already stored subgroups
+                    // (But only if there were any)
+                    if cell.is_empty() {
+                        if cell_num != 0 {
+                            subgroups.push(subgroup_numbers);
+                        }
+                        // if !subgroups.is_empty() {
+                        //     subgroups.push(subgroup_numbers);
+                        // }
+                        subgroup_numbers = None;
+                    } else {
+                        assert!(cell.is_string());
+                        if subgroup_numbers.is_none() {
+                            // This means that we are at the start of a new group
+                            // So we push None to subgroups to signalize that previous group hadn't subgroups
+                            // (but only if it isn't the first group)
+                            if cell_num != 0 {
+                                subgroups.push(None);
+                            }
+                            subgroup_numbers = Some(Vec::with_capacity(3));
+                        }
+                        let subgroup_numbers_vec = subgroup_numbers.as_mut().unwrap();
+                        // If the last element is higher than this one
+                        // It means that we are at the start of a new group of subgroups
+                        // push the previous vec to subgroups and create a new one with the first subgroup number
+                        // Else we just continue adding numbers to the same vec
+                        let parsed = parse_datacell(cell);
+                        if subgroup_numbers_vec
+                            .last()
+                            .map(|last| last > &parsed)
+                            .unwrap_or_default()
+                        {
+                            let mut new_vec = vec![parsed];
+                            std::mem::swap(&mut new_vec, subgroup_numbers_vec);
+                            subgroups.push(Some(new_vec));
+                        } else {
+                            subgroup_numbers_vec.push(parsed)
+                        }
+                        // subgroup_numbers = Some(subgroup_numbers_vec);
+                    }
+                }
+                // Push the last subgroup numbers
+                subgroups.push(subgroup_numbers)
+            }
+
+            let subgroups_num = subgroups
+                .iter()
+                .map(|el| el.as_ref().map(|el| el.len()).unwrap_or(1))
+                .sum::<usize>();
+
+            let mut classes: Vec<Week> = Vec::with_capacity(subgroups_num);
+            // Every sheet is parsed on its own thread, so each one has its own interner
+            let mut interner = Interner::default();
+
+            for _ in 0..classes.capacity() {
+                classes.push(Week::default());
+            }
+
+            for (row_count, (upper, lower)) in rows.tuple_windows().step_by(2).enumerate() {
+                assert!(
+                    row_count <= 7 * 7,
+                    "Too many rows in a sheet, got {}",
+                    row_count
+                );
+                // Monday is 0, Tuesday is 1, etc.
+                let day_num = row_count / 7;
+                assert!(day_num <= 6, "Too many days in a sheet, got {}", day_num);
+                // First lesson is 0, second is 1, etc.
+                let lesson_num = row_count % 7;
+                assert!(
+                    lesson_num <= 6,
+                    "Too many lessons in a sheet, got {}",
+                    lesson_num
+                );
+
+                assert_eq!(
+                    (upper.len() - 3) / 2,
+                    subgroups_num,
+                    "Upper has wrong length, got {}, expected {}",
+                    (upper.len() - 3) / 2,
+                    subgroups_num
+                );
+                assert_eq!(
+                    (lower.len() - 3) / 2,
+                    subgroups_num,
+                    "Lower has wrong length, got {}, expected {}",
+                    (lower.len() - 3) / 2,
+                    subgroups_num
+                );
+
+                let upper_iter = upper.iter().skip(3).tuple_windows().step_by(2);
+                let lower_iter = lower.iter().skip(3).tuple_windows().step_by(2);
+                for (
+                    column_num,
+                    ((name_and_teacher_upper, room_upper), (name_and_teacher_lower, room_lower)),
+                ) in upper_iter.zip(lower_iter).enumerate()
+                {
+                    assert!(
+                        column_num < subgroups_num,
+                        "Too many columns in a sheet, got {}, expected max: {}",
+                        column_num,
+                        subgroups_num
+                    );
+                    let day = &mut classes[column_num][day_num];
+                    let class_upper = Class::new(name_and_teacher_upper, room_upper, &mut interner);
+                    let class_lower = Class::new(name_and_teacher_lower, room_lower, &mut interner);
+                    day.upper_classes[lesson_num] = class_upper;
+                    day.lower_classes[lesson_num] = class_lower;
+                }
+            }
+
+            // // A vector that contains all the parsed days
+            // let mut classes: Vec<Day> = Vec::with_capacity(subgroups_num * 7);
+            // let classes_num = classes.capacity();
+
+            // // Fill classes with default value
+            // for _ in 0..classes_num {
+            //     classes.push(Day::default());
+            // }
+
+            // for (mut class_number, (upper, lower)) in rows.tuple_windows().step_by(2).enumerate() {
+            //     // Monday is 0, Tuesday is 1, etc.
+            //     let day_number = class_number / 7;
+            //     assert!(
+            //         day_number < 7,
+            //         "There are more than 7 days in a week, which is impossible"
+            //     );
+            //     class_number %= 7;
+            //     for (
+            //         ((name_and_teacher_upper, room_upper), (name_and_teacher_lower, room_lower)),
+            //         day,
+            //     ) in upper
+            //         .iter()
+            //         .skip(3)
+            //         .tuple_windows()
+            //         .zip(lower.iter().skip(3).tuple_windows())
+            //         .zip(classes.iter_mut().skip(day_number * subgroups_num))
+            //     {
+            //         let class_upper = Class::new(name_and_teacher_upper, room_upper);
+            //         let class_lower = Class::new(name_and_teacher_lower, room_lower);
+            //         day.upper_classes[class_number] = class_upper;
+            //         day.lower_classes[class_number] = class_lower;
+            //     }
+            // }
+
+            //println!(
+            //    "{}",
+            //    simd_json::serde::to_string_pretty(classes.as_slice()).unwrap()
+            //);
+
+            // Classes is structured like [Monday * subgroups_num, Tuesday * subgroups_num, ...]
+            // We need to split it into [Monday, Tuesday, ...] * subgroups_num
+            // let mut thing: Vec<[Day; 7]> = Vec::with_capacity(subgroups_num);
+
+            // for _ in 0..thing.capacity() {
+            //     thing.push([
+            //         Day::default(),
+            //         Day::default(),
+            //         Day::default(),
+            //         Day::default(),
+            //         Day::default(),
+            //         Day::default(),
+            //         Day::default(),
+            //     ]);
+            // }
+
+            // let classes = classes.chunks(subgroups_num).enumerate().into_iter();
+
+            // for (day, classes) in classes
+            //     .into_iter()
+            //     .chunks(subgroups_num)
+            //     .into_iter()
+            //     .enumerate()
+            // {
+            //     for (subgroup, class) in classes.into_iter().enumerate() {
+            //         thing[subgroup][day] = class;
+            //     }
+            // }
+
+            // assert_eq!(
+            //     thing.len(),
+            //     subgroups
+            //         .iter()
+            //         .map(|el| el.as_ref().map(|el| el.len()).unwrap_or(1))
+            //         .sum::<usize>(),
+            //     "Have {} subgroups, but {} weeks",
+            //     subgroups_num,
+            //     thing.len()
+            // );
+            let mut week_iter = classes.into_iter();
+
+            let groups = first_row
+                .zip(subgroups)
+                .map(|(cell, subgroup)| {
+                    if let DataType::String(name) = cell {
+                        let name = name.clone();
+                        if let Some(subgroups) = subgroup {
+                            GroupInfo {
+                                name,
+                                subgroups: WeekInfo::WithSubgroups(
+                                    subgroups
+                                        .into_iter()
+                                        .zip(&mut week_iter)
+                                        .map(|(el, week)| Subgroup {
+                                            number: el,
+                                            days: week,
+                                        })
+                                        .collect(),
+                                ),
+                            }
+                        } else {
+                            GroupInfo {
+                                name,
+                                subgroups: WeekInfo::WithoutSubgroup(week_iter.next().unwrap()),
+                            }
+                        }
+                    } else {
+                        unreachable!()
+                    }
+                })
+                .collect::<Vec<_>>();
+            Course::new(name, groups)
+        });
+
+        courses_iter
+            .zip(courses.par_iter_mut())
+            .for_each(|(got, store)| {
+                store.write(got);
+            });
+
+        // SAFETY: Just initialized it
+        unsafe { courses.map(|el| el.assume_init()) }
+    }
+}
+
+trait Swappable {
+    type Output;
+
+    fn swap(self) -> Self::Output;
+}
+
+impl<T1, T2> Swappable for (T1, T2) {
+    type Output = (T2, T1);
+
+    fn swap(self) -> Self::Output {
+        let (a, b) = self;
+        (b, a)
+    }
+}
+
+impl Display for GroupInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
@@ -0,0 +1,391 @@
+use crate::alisa::{self, InputType, Interfaces};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Information about the device the user talks with Marusia on
+pub struct Meta {
+    /// Identifier of the device and application, don't rely on its format
+    pub client_id: String,
+    /// Language, e.g. `ru_RU`
+    pub locale: String,
+    /// Name of the user's time zone, e.g. `Europe/Moscow`
+    pub timezone: String,
+    /// Interfaces that are available on the user's device, the same as Alice's
+    #[serde(default)]
+    pub interfaces: Interfaces,
+    /// The user's city in Russian
+    #[serde(rename = "_city_ru")]
+    pub city: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+/// Words and entities Marusia found in the message
+pub struct Nlu {
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// Entities in Marusia's own format, the ones shaped like Alice's are passed to the skill
+    #[serde(default)]
+    pub entities: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Data received from a user
+pub struct Request {
+    /// Normalized text of the message
+    #[serde(default)]
+    pub command: String,
+    /// Text of the message as it was said
+    #[serde(default)]
+    pub original_utterance: String,
+    #[serde(rename = "type")]
+    pub request_type: InputType,
+    /// Payload of the pressed button
+    pub payload: Option<Value>,
+    #[serde(default)]
+    pub nlu: Nlu,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A user logged in with a VK account
+pub struct User {
+    pub user_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// An instance of the application the user talks with
+pub struct Application {
+    pub application_id: String,
+    /// `mobile`, `speaker` or `other`
+    pub application_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Data about the dialog
+pub struct Session {
+    pub session_id: String,
+    /// Identifier of the user on the device, present even if they aren't logged in
+    pub user_id: String,
+    /// Identifier of the skill, assigned in the VK console
+    pub skill_id: String,
+    /// `true` if the user just started a dialog with the skill
+    pub new: bool,
+    /// Number of the message in the session, starting at 0
+    pub message_id: u64,
+    /// Missing if the user isn't logged in
+    pub user: Option<User>,
+    pub application: Application,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+/// Data stored by the skill. Marusia has no state of an application instance
+pub struct State {
+    #[serde(default)]
+    pub session: Value,
+    #[serde(default)]
+    pub user: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Everything Marusia sends to the skill's webhook
+pub struct WebhookRequest {
+    pub meta: Meta,
+    pub request: Request,
+    pub session: Session,
+    #[serde(default)]
+    pub state: State,
+    pub version: String,
+}
+
+impl From<&WebhookRequest> for alisa::WebhookRequest {
+    /// The request as Alice would send it.
+    /// The user is always known to Marusia, so the skill keeps everything in the user state.
+    /// Only the entities shaped like Alice's are kept
+    fn from(request: &WebhookRequest) -> Self {
+        let session = &request.session;
+        let user_id = session
+            .user
+            .as_ref()
+            .map_or(&session.user_id, |user| &user.user_id);
+        let entities = request
+            .request
+            .nlu
+            .entities
+            .iter()
+            .filter_map(|entity| alisa::Entity::deserialize(entity).ok())
+            .collect();
+        Self {
+            meta: alisa::Meta {
+                locale: request.meta.locale.replace('_', "-"),
+                timezone: request.meta.timezone.clone(),
+                client_id: request.meta.client_id.clone(),
+                interfaces: request.meta.interfaces.clone(),
+            },
+            request: alisa::Request {
+                command: request.request.command.clone(),
+                original_utterance: request.request.original_utterance.clone(),
+                markup: None,
+                nlu: alisa::Nlu {
+                    tokens: request.request.nlu.tokens.clone(),
+                    entities,
+                    intents: HashMap::new(),
+                },
+                payload: request.request.payload.clone(),
+                request_type: request.request.request_type.clone(),
+                show_type: None,
+            },
+            session: alisa::Session {
+                message_id: session.message_id,
+                session_id: session.session_id.clone(),
+                skill_id: session.skill_id.clone(),
+                user: Some(alisa::User {
+                    user_id: user_id.clone(),
+                }),
+                application: alisa::Application {
+                    application_id: session.application.application_id.clone(),
+                },
+                new: session.new,
+            },
+            state: alisa::State {
+                session: request.state.session.clone(),
+                user: request.state.user.clone(),
+                application: Value::Null,
+            },
+            version: request.version.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A button shown under the response
+pub struct Button {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// What Marusia says and shows to the user
+pub struct Response {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<Button>,
+    pub end_session: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// The session the response belongs to, copied from the request
+pub struct ResponseSession {
+    pub session_id: String,
+    pub user_id: String,
+    pub message_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Everything the skill's webhook sends back to Marusia
+pub struct WebhookResponse {
+    pub response: Response,
+    pub session: ResponseSession,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_state: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_state_update: Option<Value>,
+    pub version: String,
+}
+
+/// Marusia doesn't know Alice's pauses, e.g. `sil <[300]>`, the punctuation is enough for her
+fn without_pauses(tts: &str) -> String {
+    let mut spoken = String::new();
+    let mut rest = tts;
+    while let Some((before, after)) = rest.split_once("sil <[") {
+        spoken.push_str(before.trim_end());
+        rest = after.split_once("]>").map_or("", |(_, after)| after);
+    }
+    spoken.push_str(rest);
+    spoken
+}
+
+/// The text with the header, the items and the footer of an `ItemsList` card on separate lines.
+/// Marusia's cards only show the images uploaded to her, so the lists of text are shown as text
+/// and Alice's images are left out
+fn with_card(text: String, card: Option<alisa::Card>) -> String {
+    let Some(alisa::Card::ItemsList {
+        header,
+        items,
+        footer,
+    }) = card
+    else {
+        return text;
+    };
+    let mut lines = vec![text];
+    lines.extend(header.map(|header| header.text));
+    lines.extend(items.into_iter().map(|item| match item.description {
+        Some(description) => format!("{} — {description}", item.title),
+        None => item.title,
+    }));
+    lines.extend(footer.map(|footer| footer.text));
+    lines.join("\n")
+}
+
+impl WebhookResponse {
+    /// Alice's answer to the `request`, with the list cards turned into text, see [`with_card`]
+    pub fn from_alisa(response: alisa::WebhookResponse, request: &WebhookRequest) -> Self {
+        let alisa::WebhookResponse {
+            response,
+            session_state,
+            user_state_update,
+            application_state,
+            version,
+        } = response;
+        let buttons = response
+            .buttons
+            .into_iter()
+            .map(|button| Button {
+                title: button.title,
+                payload: button.payload,
+                url: button.url,
+            })
+            .collect();
+        // The list is shown, but only the answer is said
+        let tts = without_pauses(response.tts.as_deref().unwrap_or(&response.text));
+        Self {
+            response: Response {
+                text: with_card(response.text, response.card),
+                tts: Some(tts),
+                buttons,
+                end_session: response.end_session,
+            },
+            session: ResponseSession {
+                session_id: request.session.session_id.clone(),
+                user_id: request.session.user_id.clone(),
+                message_id: request.session.message_id,
+            },
+            session_state,
+            // Alice's skill keeps the state of logged out users in the application
+            user_state_update: user_state_update.or(application_state),
+            version,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_request() -> Value {
+    serde_json::json!({
+        "meta": {
+            "client_id": "MailRu-VC/1.0",
+            "locale": "ru_RU",
+            "timezone": "Europe/Moscow",
+            "interfaces": { "screen": {} },
+            "_city_ru": "Москва"
+        },
+        "request": {
+            "command": "когда завтра первая пара",
+            "original_utterance": "Когда завтра первая пара?",
+            "type": "SimpleUtterance",
+            "payload": {},
+            "nlu": {
+                "tokens": ["когда", "завтра", "первая", "пара"],
+                "entities": [
+                    {
+                        "tokens": { "start": 1, "end": 2 },
+                        "type": "YANDEX.DATETIME",
+                        "value": { "day": 1, "day_is_relative": true }
+                    },
+                    { "type": "VK.UNKNOWN", "value": "первая" }
+                ]
+            }
+        },
+        "session": {
+            "session_id": "574d41e0-a41e-4f0e-b2e1-8b4f2d5c7e3a",
+            "user_id": "6c3e9f0d1b2a4c5e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e",
+            "skill_id": "b5ebc2a8-2f1e-4c2e-8e5a-9c6f1d3b4a5e",
+            "new": false,
+            "message_id": 3,
+            "application": {
+                "application_id": "6c3e9f0d1b2a4c5e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e",
+                "application_type": "mobile"
+            }
+        },
+        "state": { "session": {}, "user": { "group": "БИВТ-21-15" } },
+        "version": "1.0"
+    })
+}
+
+#[test]
+fn requests_are_translated() {
+    use serde_json::json;
+
+    let request: WebhookRequest = serde_json::from_value(test_request()).unwrap();
+    assert_eq!(request.meta.city.as_deref(), Some("Москва"));
+    assert_eq!(request.session.application.application_type, "mobile");
+
+    let alisa = alisa::WebhookRequest::from(&request);
+    assert_eq!(alisa.meta.locale, "ru-RU");
+    assert!(alisa.meta.interfaces.has_screen());
+    assert_eq!(alisa.request.request_type, InputType::SimpleUtterance);
+    assert_eq!(alisa.request.nlu.tokens, request.request.nlu.tokens);
+    // Only the entity shaped like Alice's is left
+    assert_eq!(alisa.request.nlu.entities.len(), 1);
+    assert_eq!(
+        alisa.session.user.map(|user| user.user_id),
+        Some(request.session.user_id.clone())
+    );
+    assert_eq!(alisa.state.user, json!({ "group": "БИВТ-21-15" }));
+    assert_eq!(alisa.state.application, Value::Null);
+}
+
+#[test]
+fn responses_are_translated() {
+    use serde_json::json;
+
+    let request: WebhookRequest = serde_json::from_value(test_request()).unwrap();
+    let mut response = alisa::WebhookResponse::new(alisa::Response {
+        tts: Some(String::from(
+            "Завтра у вас одна пара. sil <[300]> Первой будет вторая пара.",
+        )),
+        buttons: vec![alisa::Button::with_payload(
+            "Завтра",
+            &json!({ "action": "tomorrow" }),
+        )],
+        card: Some(alisa::Card::ItemsList {
+            header: Some(alisa::CardHeader {
+                text: String::from("Вторник, 06.09"),
+            }),
+            items: vec![alisa::CardItem {
+                image_id: None,
+                title: String::from("10:50 Математика"),
+                description: Some(String::from("лекция, аудитория Б-436")),
+                button: None,
+            }],
+            footer: None,
+        }),
+        ..alisa::Response::new("Завтра первая пара в 10:50.")
+    });
+    response.application_state = Some(json!({ "group": "БИВТ-21-15", "subgroup": 1 }));
+    response.session_state = Some(json!({ "awaiting": "group" }));
+
+    assert_eq!(
+        serde_json::to_value(WebhookResponse::from_alisa(response, &request)).unwrap(),
+        json!({
+            "response": {
+                "text": "Завтра первая пара в 10:50.\nВторник, 06.09\n10:50 Математика — лекция, аудитория Б-436",
+                "tts": "Завтра у вас одна пара. Первой будет вторая пара.",
+                "buttons": [{ "title": "Завтра", "payload": { "action": "tomorrow" } }],
+                "end_session": false
+            },
+            "session": {
+                "session_id": "574d41e0-a41e-4f0e-b2e1-8b4f2d5c7e3a",
+                "user_id": "6c3e9f0d1b2a4c5e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e",
+                "message_id": 3
+            },
+            "session_state": { "awaiting": "group" },
+            "user_state_update": { "group": "БИВТ-21-15", "subgroup": 1 },
+            "version": "1.0"
+        })
+    );
+}
@@ -0,0 +1,854 @@
+use crate::{
+    alisa::{
+        relative_day, Button, Card, CardFooter, CardHeader, CardItem, InputType, Response,
+        WebhookRequest, WebhookResponse, YandexEnteties,
+    },
+    calendar,
+    store::{Schedules, Slot},
+    Class, ClassType, Week,
+};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{env, path::PathBuf, time::Duration};
+
+mod dialog;
+mod grammar;
+mod group_name;
+mod intents;
+mod onboarding;
+mod rooms;
+mod safety;
+mod show;
+pub mod simulator;
+mod speech;
+mod teachers;
+
+const GREETING: &str = "Привет! Я знаю расписание МИСиС. \
+    Спросите, например, когда завтра первая пара.";
+const NOT_UNDERSTOOD: &str = "Простите, я пока не понимаю такие вопросы. \
+    Спросите, например, когда завтра первая пара.";
+const UNKNOWN_GROUP: &str = "Я пока не знаю, в какой вы группе. \
+    Назовите её, например, БИВТ-21-15.";
+const TOO_LONG: &str = "Простите, я задумалась и не успела ответить. \
+    Спросите ещё раз, пожалуйста.";
+const FAILED: &str = "Простите, у меня что-то сломалось. Попробуйте спросить чуть позже.";
+/// How long an answer may take. Dialogs wait for 3 seconds, the rest is left for the network
+pub const DEADLINE: Duration = Duration::from_millis(2500);
+/// Most items an `ItemsList` card can have
+const MAX_CARD_ITEMS: usize = 5;
+
+/// A group the user studies in, kept in the user or application state
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UserGroup {
+    pub group: String,
+    /// [`None`] if the group isn't divided into subgroups
+    #[serde(default)]
+    pub subgroup: Option<u8>,
+}
+
+/// What a button under a schedule answer asks for, sent back in its payload
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Not shown under the answers, but sent by the Telegram bot's `/today`
+    Today,
+    Tomorrow,
+    Week,
+    NextClass,
+}
+
+impl Action {
+    fn question(self, today: NaiveDate) -> Question {
+        match self {
+            Self::Today => Question::Day(today),
+            Self::Tomorrow => Question::Day(today.succ_opt().unwrap()),
+            Self::Week => Question::Week,
+            Self::NextClass => Question::NextClass,
+        }
+    }
+
+    /// Suggests shown under every schedule answer
+    fn buttons() -> Vec<Button> {
+        [
+            ("Завтра", Self::Tomorrow),
+            ("Вся неделя", Self::Week),
+            ("Следующая пара", Self::NextClass),
+        ]
+        .into_iter()
+        .map(|(title, action)| Button::with_payload(title, &action))
+        .collect()
+    }
+}
+
+/// What the user wants to know about their schedule
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Question {
+    /// When the classes of a day start
+    Day(NaiveDate),
+    /// How many classes there are on each day of the week
+    Week,
+    /// Which class is the next one
+    NextClass,
+}
+
+/// Settings of the skill that don't change between requests
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// The first day of the semester, its week is an upper one
+    /// The 1st of September of the current academic year if [`None`]
+    pub semester_start: Option<NaiveDate>,
+    /// Group of the users who didn't tell theirs
+    pub default_group: Option<UserGroup>,
+    /// Said to the messages that Dialogs mark as dangerous and to the profane ones,
+    /// [`safety::DANGEROUS_REPLY`] if [`None`]
+    pub dangerous_reply: Option<String>,
+    /// JSON lines file the dangerous messages are logged to, stderr if [`None`]
+    pub dangerous_log: Option<PathBuf>,
+    /// Who may call `/api/alisa-trigger`
+    pub alice: Access,
+    /// Who may call `/api/marusia-trigger`
+    pub marusia: Access,
+}
+
+/// Who may call a webhook of the skill
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    /// Skills whose requests are answered, any skill's if empty and the secret is set
+    pub skill_ids: Vec<String>,
+    /// Has to be passed as `?secret=` in the webhook's URL, if set.
+    /// Without it and without the skill ids no request is answered
+    pub secret: Option<String>,
+}
+
+impl Access {
+    /// Reads the skill ids separated by commas from the `skill_ids` variable
+    /// and the secret from the `secret` one
+    fn from_env(skill_ids: &str, secret: &str) -> Self {
+        Self {
+            skill_ids: env::var(skill_ids)
+                .map(|ids| {
+                    ids.split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            secret: env::var(secret).ok(),
+        }
+    }
+
+    /// Neither the skills nor the secret are configured, so every request is rejected
+    pub fn is_closed(&self) -> bool {
+        self.skill_ids.is_empty() && self.secret.is_none()
+    }
+}
+
+impl Config {
+    /// Reads `MISISA_SEMESTER_START` as `YYYY-MM-DD`,
+    /// `MISISA_DEFAULT_GROUP`, `MISISA_DEFAULT_SUBGROUP`,
+    /// `MISISA_DANGEROUS_REPLY`, `MISISA_DANGEROUS_LOG`,
+    /// `MISISA_SKILL_IDS` separated by commas and `MISISA_ALICE_SECRET`,
+    /// `MISISA_MARUSIA_SKILL_IDS` and `MISISA_MARUSIA_SECRET` alike
+    pub fn from_env() -> Self {
+        let semester_start = env::var("MISISA_SEMESTER_START")
+            .ok()
+            .map(|date| date.parse().expect("MISISA_SEMESTER_START is not a date"));
+        let default_group = env::var("MISISA_DEFAULT_GROUP")
+            .ok()
+            .map(|group| UserGroup {
+                group,
+                subgroup: env::var("MISISA_DEFAULT_SUBGROUP").ok().map(|subgroup| {
+                    subgroup
+                        .parse()
+                        .expect("MISISA_DEFAULT_SUBGROUP is not a number")
+                }),
+            });
+        Self {
+            semester_start,
+            default_group,
+            dangerous_reply: env::var("MISISA_DANGEROUS_REPLY").ok(),
+            dangerous_log: env::var_os("MISISA_DANGEROUS_LOG").map(PathBuf::from),
+            alice: Access::from_env("MISISA_SKILL_IDS", "MISISA_ALICE_SECRET"),
+            marusia: Access::from_env("MISISA_MARUSIA_SKILL_IDS", "MISISA_MARUSIA_SECRET"),
+        }
+    }
+
+    fn semester_start(&self, date: NaiveDate) -> NaiveDate {
+        self.semester_start
+            .unwrap_or_else(|| calendar::academic_year_start(date))
+    }
+}
+
+/// Everything a request is answered with
+pub struct Context<'a> {
+    pub schedules: &'a Schedules,
+    /// Current time in Moscow
+    pub now: DateTime<FixedOffset>,
+    pub config: &'a Config,
+    /// The schedules as they were a day ago, to tell about the changes in the morning show
+    pub yesterday: Option<&'a Schedules>,
+}
+
+impl<'a> Context<'a> {
+    /// A context with nothing known about yesterday
+    pub const fn new(
+        schedules: &'a Schedules,
+        now: DateTime<FixedOffset>,
+        config: &'a Config,
+    ) -> Self {
+        Self {
+            schedules,
+            now,
+            config,
+            yesterday: None,
+        }
+    }
+}
+
+/// Answers a message that Alice forwarded to the skill,
+/// leaving out the cards and buttons if the device has no screen
+pub fn handle(request: &WebhookRequest, context: &Context) -> WebhookResponse {
+    let mut response = answer(request, context);
+    response.response.fit(&request.meta.interfaces);
+    response
+}
+
+/// The request came from an allowed skill, with the `secret` from the webhook's URL if one is set.
+/// Nothing is allowed if neither the skills nor the secret are configured
+pub fn is_allowed(request: &WebhookRequest, secret: Option<&str>, access: &Access) -> bool {
+    if access.is_closed() {
+        return false;
+    }
+    let skill_id = &request.session.skill_id;
+    let known_skill = access.skill_ids.is_empty() || access.skill_ids.contains(skill_id);
+    let secret_matches = access.secret.as_deref().is_none_or(|expected| {
+        // Digests are compared, so the time the comparison takes tells nothing about the secret
+        let digest = |secret: &str| Sha256::digest(secret.as_bytes());
+        secret.is_some_and(|secret| digest(secret) == digest(expected))
+    });
+    known_skill && secret_matches
+}
+
+/// Answers a ping right away and anything else within the `deadline`.
+/// `handle` runs on a blocking thread, if it doesn't finish in time the user is asked to repeat,
+/// if it panics the user is told that something broke
+pub async fn respond(
+    request: WebhookRequest,
+    deadline: Duration,
+    handle: impl FnOnce(&WebhookRequest) -> WebhookResponse + Send + 'static,
+) -> WebhookResponse {
+    if request.request.is_ping() {
+        return WebhookResponse::new(Response::new("pong"));
+    }
+    let answer = tokio::task::spawn_blocking(move || handle(&request));
+    match tokio::time::timeout(deadline, answer).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(error)) => {
+            eprintln!("Skill handler failed: {error:?}");
+            WebhookResponse::new(Response::new(FAILED))
+        }
+        Err(_) => {
+            eprintln!("Skill handler didn't answer in {deadline:?}");
+            WebhookResponse::new(Response::new(TOO_LONG))
+        }
+    }
+}
+
+fn answer(request: &WebhookRequest, context: &Context) -> WebhookResponse {
+    if request.request.request_type == InputType::ShowPull {
+        return show::morning(request, context);
+    }
+    if let Some(reason) = safety::is_dangerous(request) {
+        let entry = safety::LogEntry::new(request, reason, context.now);
+        safety::log(&entry, context.config.dangerous_log.as_deref());
+        let reply = context
+            .config
+            .dangerous_reply
+            .as_deref()
+            .unwrap_or(safety::DANGEROUS_REPLY);
+        return WebhookResponse::new(Response::new(reply));
+    }
+    let tokens = &request.request.nlu.tokens;
+    if onboarding::is_change_request(tokens) {
+        return onboarding::change_group(request, context.now);
+    }
+    match dialog::pending(request, context.now) {
+        Some(awaiting) => {
+            if let Some(response) = dialog::resume(awaiting, request, context) {
+                return response;
+            }
+        }
+        None if onboarding::mentions_group(tokens) => {
+            return onboarding::capture(request, context, None);
+        }
+        None => {}
+    }
+    if let Some(action) = request.request.payload::<Action>() {
+        let question = action.question(context.now.date_naive());
+        return schedule(request, context, question);
+    }
+    if let Some(response) = intents::dispatch(request, context) {
+        return response;
+    }
+    let text = if request.session.new {
+        GREETING
+    } else {
+        NOT_UNDERSTOOD
+    };
+    WebhookResponse::new(Response::new(text))
+}
+
+/// The user's group from the user state, then the application state, then the config
+fn user_group(request: &WebhookRequest, config: &Config) -> Option<UserGroup> {
+    let state = &request.state;
+    [&state.user, &state.application]
+        .into_iter()
+        .find_map(|state| UserGroup::deserialize(state).ok())
+        .or_else(|| config.default_group.clone())
+}
+
+/// The day the user asks about, today if they don't name one
+fn requested_date(request: &WebhookRequest, now: DateTime<FixedOffset>) -> NaiveDate {
+    let nlu = &request.request.nlu;
+    let today = now.date_naive();
+    let from_entity = nlu
+        .entities
+        .iter()
+        .find_map(|entity| match &entity.named_entity {
+            YandexEnteties::DateTime(date_time) => date_time.resolve(now),
+            _ => None,
+        });
+    from_entity
+        .map(|resolved| resolved.date())
+        .or_else(|| {
+            nlu.tokens
+                .iter()
+                .find_map(|token| relative_day(token, today))
+        })
+        .unwrap_or(today)
+}
+
+/// How the day is called in an answer, e.g. `Завтра` or `В пятницу`
+fn day_name(date: NaiveDate, today: NaiveDate) -> &'static str {
+    match (date - today).num_days() {
+        0 => return "Сегодня",
+        1 => return "Завтра",
+        2 => return "Послезавтра",
+        _ => {}
+    }
+    match date.weekday() {
+        Weekday::Mon => "В понедельник",
+        Weekday::Tue => "Во вторник",
+        Weekday::Wed => "В среду",
+        Weekday::Thu => "В четверг",
+        Weekday::Fri => "В пятницу",
+        Weekday::Sat => "В субботу",
+        Weekday::Sun => "В воскресенье",
+    }
+}
+
+fn class_type_name(class_type: &ClassType) -> String {
+    match class_type {
+        ClassType::Lection => String::from("лекция"),
+        ClassType::Practice => String::from("практика"),
+        ClassType::Lab => String::from("лабораторная"),
+        ClassType::Unknown(name) => name.to_lowercase(),
+    }
+}
+
+/// Answers a question about the schedule of the user's group
+fn schedule(request: &WebhookRequest, context: &Context, question: Question) -> WebhookResponse {
+    let Some(user_group) = user_group(request, context.config) else {
+        return onboarding::ask_group(UNKNOWN_GROUP, context.now);
+    };
+    let Some(week) = context
+        .schedules
+        .find_week(&user_group.group, user_group.subgroup)
+    else {
+        return WebhookResponse::new(Response::new(format!(
+            "Я не нашла расписание группы {}.",
+            user_group.group
+        )));
+    };
+    let mut response = match question {
+        Question::Day(date) => first_class(context, week, date),
+        Question::Week => week_overview(context, week),
+        Question::NextClass => next_class(context, week),
+    };
+    response.buttons = Action::buttons();
+    WebhookResponse::new(response)
+}
+
+/// `Понедельник`, the name of a weekday on its own
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Понедельник",
+        Weekday::Tue => "Вторник",
+        Weekday::Wed => "Среда",
+        Weekday::Thu => "Четверг",
+        Weekday::Fri => "Пятница",
+        Weekday::Sat => "Суббота",
+        Weekday::Sun => "Воскресенье",
+    }
+}
+
+/// A card with the classes of a day: their time, name, type, room and teacher
+fn day_card(date: NaiveDate, classes: &[(Slot, &Class)]) -> Card {
+    let items = classes
+        .iter()
+        .take(MAX_CARD_ITEMS)
+        .map(|(slot, class)| {
+            let time = calendar::format_time(calendar::lesson_start(slot.lesson));
+            let mut description = format!(
+                "{}, аудитория {}",
+                class_type_name(&class.class_type),
+                class.room
+            );
+            if let Some(teacher) = &class.teacher {
+                description = format!("{description}, {teacher}");
+            }
+            CardItem {
+                image_id: None,
+                title: format!("{time} {}", class.name),
+                description: Some(description),
+                button: None,
+            }
+        })
+        .collect();
+    let hidden = classes.len().saturating_sub(MAX_CARD_ITEMS);
+    Card::ItemsList {
+        header: Some(CardHeader {
+            text: format!("{}, {}", weekday_name(date.weekday()), date.format("%d.%m")),
+        }),
+        items,
+        footer: (hidden > 0).then(|| CardFooter {
+            text: format!("И ещё {hidden}"),
+            button: None,
+        }),
+    }
+}
+
+/// Answers when the first class of a day starts and shows all the classes of the day.
+/// The spoken answer also tells how many classes there are and which one is the first
+fn first_class(context: &Context, week: &Week, date: NaiveDate) -> Response {
+    let today = context.now.date_naive();
+    let day = day_name(date, today);
+    let semester_start = context.config.semester_start(date);
+    let classes: Vec<_> = calendar::day_classes(week, semester_start, date).collect();
+    let Some(&(slot, class)) = classes.first() else {
+        return Response::new(format!("{day} у вас нет пар."));
+    };
+    let time = calendar::format_time(calendar::lesson_start(slot.lesson));
+    let class_type = class_type_name(&class.class_type);
+    let text = format!(
+        "{day} первая пара в {time} — {}, {class_type}, аудитория {}.",
+        class.name, class.room,
+    );
+    let tts = format!(
+        "{day} у вас {}. {} Первой будет {} в {time}. {} {}, {}, аудитория {}.",
+        speech::classes_count(classes.len() as u32),
+        speech::PAUSE,
+        speech::ordinal_class(slot.lesson),
+        speech::PAUSE,
+        speech::expand(&class.name),
+        speech::expand(&class_type),
+        speech::room(&class.room),
+    );
+    Response {
+        tts: Some(tts),
+        card: Some(day_card(date, &classes)),
+        ..Response::new(text)
+    }
+}
+
+/// Answers with the first class that hasn't started yet, looking up to a week ahead
+fn next_class(context: &Context, week: &Week) -> Response {
+    let today = context.now.date_naive();
+    let now = context.now.time();
+    let upcoming = today.iter_days().take(7).find_map(|date| {
+        let semester_start = context.config.semester_start(date);
+        let classes: Vec<_> = calendar::day_classes(week, semester_start, date)
+            .filter(|(slot, _)| date > today || calendar::lesson_start(slot.lesson) > now)
+            .collect();
+        (!classes.is_empty()).then_some((date, classes))
+    });
+    let Some((date, classes)) = upcoming else {
+        return Response::new("На этой неделе у вас больше нет пар.");
+    };
+    let (slot, class) = classes[0];
+    let time = calendar::format_time(calendar::lesson_start(slot.lesson));
+    let day = day_name(date, today).to_lowercase();
+    let class_type = class_type_name(&class.class_type);
+    let text = format!(
+        "Следующая пара {day} в {time} — {}, {class_type}, аудитория {}.",
+        class.name, class.room,
+    );
+    let tts = format!(
+        "Следующая пара {day} в {time}. {} {}, {}, аудитория {}.",
+        speech::PAUSE,
+        speech::expand(&class.name),
+        speech::expand(&class_type),
+        speech::room(&class.room),
+    );
+    Response {
+        tts: Some(tts),
+        card: Some(day_card(date, &classes)),
+        ..Response::new(text)
+    }
+}
+
+/// Tells how many classes there are on each day of the current week
+fn week_overview(context: &Context, week: &Week) -> Response {
+    let today = context.now.date_naive();
+    let monday = today - chrono::Days::new(today.weekday().num_days_from_monday().into());
+    let days: Vec<(NaiveDate, u32)> = monday
+        .iter_days()
+        .take(6)
+        .map(|date| {
+            let semester_start = context.config.semester_start(date);
+            let count = calendar::day_classes(week, semester_start, date).count() as u32;
+            (date, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    if days.is_empty() {
+        return Response::new("На этой неделе у вас нет пар.");
+    }
+    let list = |count: fn(u32) -> String, separator: &str| {
+        days.iter()
+            .map(|(date, classes)| {
+                format!(
+                    "{} — {}",
+                    weekday_name(date.weekday()).to_lowercase(),
+                    count(*classes)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    let text = format!(
+        "На этой неделе: {}.",
+        list(
+            |count| format!("{count} {}", speech::plural(count, ["пара", "пары", "пар"])),
+            ", "
+        )
+    );
+    let tts = format!(
+        "На этой неделе: {}.",
+        list(speech::classes_count, &format!(", {} ", speech::PAUSE))
+    );
+    Response {
+        tts: Some(tts),
+        ..Response::new(text)
+    }
+}
+
+/// БИВТ-21-15 with two subgroups, the first one has a lecture on Monday of a lower week,
+/// and БПМ-21-1 without subgroups and classes
+#[cfg(test)]
+fn test_schedules() -> Schedules {
+    use crate::{
+        store::{set_class, Slot, WeekParity},
+        Class, GroupInfo, Week,
+    };
+    use std::sync::Arc;
+
+    let mut week = Week::default();
+    let slot = Slot {
+        day: 0,
+        lesson: 1,
+        week: WeekParity::Lower,
+    };
+    let class = Class {
+        name: Arc::from("Математика"),
+        class_type: ClassType::Lection,
+        teacher: None,
+        room: Arc::from("Б-436"),
+    };
+    set_class(&mut week, slot, class);
+    let groups = vec![
+        GroupInfo::from_weeks(
+            String::from("БИВТ-21-15"),
+            vec![(Some(1), week), (Some(2), Week::default())],
+        ),
+        GroupInfo::from_weeks(String::from("БПМ-21-1"), vec![(None, Week::default())]),
+    ];
+    test_course(groups)
+}
+
+/// The `groups` as the second course of ITKN
+#[cfg(test)]
+fn test_course(groups: Vec<crate::GroupInfo>) -> Schedules {
+    let mut schedules = Schedules::default();
+    schedules.insert(
+        String::from("itkn"),
+        vec![crate::Course::new(String::from("2 курс"), groups)],
+    );
+    schedules
+}
+
+/// The semester that started on Thursday, the 1st of September 2022
+#[cfg(test)]
+fn test_config() -> Config {
+    Config {
+        semester_start: NaiveDate::from_ymd_opt(2022, 9, 1),
+        ..Config::default()
+    }
+}
+
+/// Asked at `now`, an RFC 3339 time, with nothing known about yesterday
+#[cfg(test)]
+fn test_context<'a>(schedules: &'a Schedules, config: &'a Config, now: &str) -> Context<'a> {
+    let now = DateTime::parse_from_rfc3339(now).unwrap();
+    Context::new(schedules, now, config)
+}
+
+/// A message of a user who isn't logged in, sent from a phone
+#[cfg(test)]
+fn test_request(
+    tokens: &[&str],
+    entities: serde_json::Value,
+    state: serde_json::Value,
+) -> WebhookRequest {
+    let request = serde_json::json!({
+        "meta": {
+            "locale": "ru-RU",
+            "timezone": "Europe/Moscow",
+            "client_id": "ru.yandex.searchplugin/7.16 (none none; android 4.4.2)",
+            "interfaces": { "screen": {} }
+        },
+        "session": {
+            "message_id": 1,
+            "session_id": "2eac4854-fce721f3-b845abba-20d60",
+            "skill_id": "3ad36498-f5rd-4079-a14b-788652932056",
+            "application": { "application_id": "47C73714B580ED24" },
+            "new": false
+        },
+        "request": {
+            "command": tokens.join(" "),
+            "original_utterance": tokens.join(" "),
+            "nlu": { "tokens": tokens, "entities": entities, "intents": {} },
+            "type": "SimpleUtterance"
+        },
+        "state": state,
+        "version": "1.0"
+    });
+    serde_json::from_value(request).unwrap()
+}
+
+#[test]
+fn first_class_is_answered() {
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let config = test_config();
+    // Sunday, the 4th of September, the next week is a lower one
+    let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+
+    let ask = |tokens: &[&str], entities: serde_json::Value| {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        let request = test_request(tokens, entities, state);
+        handle(&request, &context).response.text
+    };
+    let speak = |tokens: &[&str]| {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        let request = test_request(tokens, json!([]), state);
+        handle(&request, &context).response.tts
+    };
+
+    assert_eq!(
+        ask(&["когда", "первая", "пара", "в", "понедельник"], json!([])),
+        "Завтра первая пара в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+    let tomorrow = json!([{
+        "tokens": { "start": 3, "end": 4 },
+        "type": "YANDEX.DATETIME",
+        "value": { "day": 1, "day_is_relative": true }
+    }]);
+    assert_eq!(
+        ask(&["когда", "первая", "пара", "завтра"], tomorrow),
+        "Завтра первая пара в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+    assert_eq!(
+        ask(&["есть", "ли", "пары", "в", "среду"], json!([])),
+        "В среду у вас нет пар."
+    );
+    assert_eq!(
+        ask(&["какие", "пары", "сегодня"], json!([])),
+        "Сегодня у вас нет пар."
+    );
+    assert_eq!(ask(&["привет"], json!([])), NOT_UNDERSTOOD);
+    assert_eq!(
+        speak(&["когда", "первая", "пара", "в", "понедельник"]).as_deref(),
+        Some(
+            "Завтра у вас одна пара. sil <[300]> Первой будет вторая пара в 10:50. \
+            sil <[300]> Математика, лекция, аудитория бэ четыреста тридцать шесть."
+        )
+    );
+    assert_eq!(speak(&["какие", "пары", "сегодня"]), None);
+
+    // Without a group the skill asks for it
+    let request = test_request(&["когда", "первая", "пара"], json!([]), json!({}));
+    let response = handle(&request, &context);
+    assert_eq!(response.response.text, UNKNOWN_GROUP);
+    assert_eq!(
+        response.session_state,
+        Some(json!({ "awaiting": "group", "asked": "2022-09-04T20:00:00+03:00" }))
+    );
+}
+
+#[test]
+fn buttons_are_answered() {
+    use crate::alisa::Interfaces;
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let config = test_config();
+    // Monday morning of a lower week, before the lecture
+    let context = test_context(&schedules, &config, "2022-09-05T09:30:00+03:00");
+    let press = |action: Action| {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        let mut request = test_request(&[], json!([]), state);
+        let button = Action::buttons()
+            .into_iter()
+            .find(|button| button.payload == Some(serde_json::to_value(action).unwrap()))
+            .unwrap();
+        request.request.payload = button.payload;
+        request.request.request_type = crate::alisa::InputType::ButtonPressed;
+        handle(&request, &context).response
+    };
+
+    let next = press(Action::NextClass);
+    assert_eq!(
+        next.text,
+        "Следующая пара сегодня в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+    assert_eq!(
+        serde_json::to_value(&next.card).unwrap(),
+        json!({
+            "type": "ItemsList",
+            "header": { "text": "Понедельник, 05.09" },
+            "items": [{ "title": "10:50 Математика", "description": "лекция, аудитория Б-436" }]
+        })
+    );
+    let titles: Vec<&str> = next
+        .buttons
+        .iter()
+        .map(|button| button.title.as_str())
+        .collect();
+    assert_eq!(titles, ["Завтра", "Вся неделя", "Следующая пара"]);
+
+    let week = press(Action::Week);
+    assert_eq!(week.text, "На этой неделе: понедельник — 1 пара.");
+    assert_eq!(
+        week.tts.as_deref(),
+        Some("На этой неделе: понедельник — одна пара.")
+    );
+    assert_eq!(press(Action::Tomorrow).text, "Завтра у вас нет пар.");
+
+    // The same questions asked with words
+    let ask = |tokens: &[&str]| {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        handle(&test_request(tokens, json!([]), state), &context).response
+    };
+    assert_eq!(ask(&["какая", "следующая", "пара"]).text, next.text);
+    assert_eq!(ask(&["пары", "на", "всю", "неделю"]).text, week.text);
+
+    // A speaker only says the answer
+    let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+    let mut request = test_request(&["какая", "следующая", "пара"], json!([]), state);
+    request.meta.interfaces = Interfaces::default();
+    let spoken = handle(&request, &context).response;
+    assert_eq!(spoken.text, next.text);
+    assert_eq!(spoken.card, None);
+    assert!(spoken.buttons.is_empty());
+}
+
+#[tokio::test]
+async fn slow_answers_are_cut_off() {
+    use serde_json::json;
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        sync::Arc,
+        thread,
+    };
+
+    let deadline = Duration::from_millis(100);
+    let schedules = Arc::new(test_schedules());
+    // A store that takes longer than the deadline to look the schedule up
+    let slow = |delay: Duration| {
+        let schedules = schedules.clone();
+        move |request: &WebhookRequest| {
+            thread::sleep(delay);
+            let config = test_config();
+            let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+            handle(request, &context)
+        }
+    };
+    let request = || {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        test_request(&["когда", "первая", "пара"], json!([]), state)
+    };
+
+    let response = respond(request(), deadline, slow(Duration::ZERO)).await;
+    assert_eq!(response.response.text, "Сегодня у вас нет пар.");
+    let response = respond(request(), deadline, slow(deadline * 5)).await;
+    assert_eq!(response.response.text, TOO_LONG);
+    // Printing the panic's backtrace may take longer than the short deadline
+    let response = respond(request(), Duration::from_secs(10), |_| {
+        panic!("The handler is broken")
+    })
+    .await;
+    assert_eq!(response.response.text, FAILED);
+
+    // Ping doesn't wait for the store at all
+    let called = Arc::new(AtomicBool::new(false));
+    let mut ping = request();
+    ping.request.original_utterance = String::from("ping");
+    let handler_called = called.clone();
+    let response = respond(ping, deadline, move |_| {
+        handler_called.store(true, Ordering::SeqCst);
+        WebhookResponse::new(Response::default())
+    })
+    .await;
+    assert_eq!(response.response.text, "pong");
+    assert!(!called.load(Ordering::SeqCst));
+}
+
+#[test]
+fn requests_are_checked() {
+    use serde_json::json;
+
+    let request = test_request(&["привет"], json!([]), json!({}));
+    let skill_id = request.session.skill_id.clone();
+    // Not configured, so anyone could call the webhook
+    assert!(!is_allowed(&request, None, &Access::default()));
+    assert!(!is_allowed(&request, Some("key"), &Access::default()));
+
+    let access = Access {
+        skill_ids: Vec::new(),
+        secret: Some(String::from("key")),
+    };
+    assert!(is_allowed(&request, Some("key"), &access));
+    assert!(!is_allowed(&request, None, &access));
+    let access = Access {
+        skill_ids: vec![skill_id.clone()],
+        secret: None,
+    };
+    assert!(is_allowed(&request, None, &access));
+
+    let access = Access {
+        skill_ids: vec![String::from("another-skill"), skill_id],
+        secret: Some(String::from("key")),
+    };
+    assert!(is_allowed(&request, Some("key"), &access));
+    assert!(!is_allowed(&request, Some("kez"), &access));
+    assert!(!is_allowed(&request, None, &access));
+    let access = Access {
+        skill_ids: vec![String::from("another-skill")],
+        secret: None,
+    };
+    assert!(!is_allowed(&request, None, &access));
+}
@@ -0,0 +1,117 @@
+use super::{intents, onboarding, teachers, Context};
+use crate::alisa::{Response, WebhookRequest, WebhookResponse};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+/// How long the skill waits for the answer to its question
+pub const FOLLOW_UP_TIMEOUT: TimeDelta = TimeDelta::minutes(5);
+
+/// A question the skill asked and waits an answer to in the next message
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(tag = "awaiting", rename_all = "snake_case")]
+pub enum Awaiting {
+    Group,
+    Subgroup {
+        group: String,
+    },
+    /// Which one of the teachers with the same surname the user meant
+    Teacher {
+        candidates: Vec<String>,
+    },
+}
+
+/// The state of the dialog, kept by Dialogs in `session_state` until the next message
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct FollowUp {
+    #[serde(flatten)]
+    awaiting: Awaiting,
+    /// When the question was asked, in RFC 3339
+    asked: String,
+}
+
+/// Answers with a question, the answer to it comes to [`resume`] with the next message
+pub fn ask(response: Response, awaiting: Awaiting, now: DateTime<FixedOffset>) -> WebhookResponse {
+    let follow_up = FollowUp {
+        awaiting,
+        asked: now.to_rfc3339(),
+    };
+    let mut response = WebhookResponse::new(response);
+    response.session_state = Some(serde_json::to_value(follow_up).unwrap());
+    response
+}
+
+/// The question asked in the previous message of the session,
+/// [`None`] if there was none or the user took too long to answer
+pub fn pending(request: &WebhookRequest, now: DateTime<FixedOffset>) -> Option<Awaiting> {
+    let follow_up = FollowUp::deserialize(&request.state.session).ok()?;
+    let asked = DateTime::parse_from_rfc3339(&follow_up.asked).ok()?;
+    (now - asked <= FOLLOW_UP_TIMEOUT).then_some(follow_up.awaiting)
+}
+
+/// Passes the answer to the handler of the question,
+/// [`None`] if the message isn't an answer to it
+pub fn resume(
+    awaiting: Awaiting,
+    request: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    let nlu = &request.request.nlu;
+    match awaiting {
+        // Another question instead of the group, e.g. "какая следующая пара", is answered as usual
+        Awaiting::Group
+            if !onboarding::may_name_group(&nlu.tokens) && intents::is_recognised(nlu) =>
+        {
+            None
+        }
+        Awaiting::Group | Awaiting::Subgroup { .. } => {
+            Some(onboarding::capture(request, context, Some(awaiting)))
+        }
+        Awaiting::Teacher { candidates } => teachers::clarify(request, context, &candidates),
+    }
+}
+
+#[test]
+fn follow_ups_expire() {
+    use super::{handle, test_config, test_context, test_request, test_schedules};
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let config = test_config();
+    let asked = DateTime::parse_from_rfc3339("2022-09-04T20:00:00+03:00").unwrap();
+    let session = ask(Response::new(""), Awaiting::Group, asked)
+        .session_state
+        .unwrap();
+    assert_eq!(
+        session,
+        json!({ "awaiting": "group", "asked": "2022-09-04T20:00:00+03:00" })
+    );
+
+    let answer = |now: &str| {
+        let context = test_context(&schedules, &config, now);
+        let state = json!({ "session": session });
+        handle(
+            &test_request(&["бпм", "21", "1"], json!([]), state),
+            &context,
+        )
+        .response
+        .text
+    };
+    assert!(answer("2022-09-04T20:05:00+03:00").starts_with("Запомнила: группа БПМ-21-1."));
+    assert_eq!(answer("2022-09-04T20:05:01+03:00"), super::NOT_UNDERSTOOD);
+
+    // Asked something else instead of the group, the skill answers it and stops waiting
+    let context = test_context(&schedules, &config, "2022-09-04T20:01:00+03:00");
+    let state = json!({
+        "session": session,
+        "application": { "group": "БИВТ-21-15", "subgroup": 1 }
+    });
+    let response = handle(
+        &test_request(&["какая", "следующая", "пара"], json!([]), state),
+        &context,
+    );
+    assert_eq!(
+        response.response.text,
+        "Следующая пара завтра в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+    assert_eq!(response.session_state, None);
+}
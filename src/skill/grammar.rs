@@ -0,0 +1,253 @@
+use super::speech;
+use crate::alisa::{weekday, Intent, Nlu, Slot, SlotValue, Token, YandexEnteties};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Kinds of the built-in entities that fill the slots of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntityKind {
+    DateTime,
+    Fio,
+    Geo,
+}
+
+impl EntityKind {
+    const fn matches(self, entity: &YandexEnteties) -> bool {
+        match self {
+            Self::DateTime => matches!(entity, YandexEnteties::DateTime(_)),
+            Self::Fio => matches!(entity, YandexEnteties::Fio(_)),
+            Self::Geo => matches!(entity, YandexEnteties::Geo(_)),
+        }
+    }
+}
+
+/// Entities defined in the skill's console
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Custom {
+    /// A day of the week, e.g. "в среду" is `wednesday`
+    Weekday,
+    /// A letter of a building after the word "корпус", e.g. "в корпусе бэ" is `Б`
+    Building,
+}
+
+impl Custom {
+    const fn entity_type(self) -> &'static str {
+        match self {
+            Self::Weekday => "Weekday",
+            Self::Building => "Building",
+        }
+    }
+
+    /// Position of the word that names the entity and the entity's value
+    fn find(self, tokens: &[String]) -> Option<(usize, Value)> {
+        match self {
+            Self::Weekday => tokens.iter().enumerate().find_map(|(position, token)| {
+                Some((position, Value::from(weekday_value(weekday(token)?))))
+            }),
+            Self::Building => {
+                let position = tokens
+                    .iter()
+                    .position(|token| token.starts_with("корпус"))?
+                    + 1;
+                let word = tokens.get(position)?;
+                let mut chars = word.chars();
+                let written = chars
+                    .next()
+                    .filter(|_| chars.next().is_none())
+                    .and_then(|letter| letter.to_uppercase().next())
+                    .filter(|letter| speech::letter_name(*letter).is_some());
+                let letter = speech::named_letter(word).or(written)?;
+                Some((position, Value::from(letter.to_string())))
+            }
+        }
+    }
+}
+
+/// A local replacement of a grammar from the skill's console
+struct Rule {
+    intent: &'static str,
+    /// Each group needs one of its words, or a word that starts with one of its stems marked by `*`
+    required: &'static [&'static [&'static str]],
+    /// Slots filled with the first entity of a kind
+    entities: &'static [(&'static str, EntityKind)],
+    /// Slots filled with the console entities found in the words
+    custom: &'static [(&'static str, Custom)],
+}
+
+const CLASSES: &[&str] = &[
+    "пара",
+    "пары",
+    "пар",
+    "паре",
+    "пару",
+    "парой",
+    "парам",
+    "парами",
+    "парах",
+    "заняти*",
+];
+
+/// Rules in the same order as [`super::intents::REGISTRY`]
+const RULES: &[Rule] = &[
+    Rule {
+        intent: "teacher.where",
+        required: &[&["где"]],
+        entities: &[("teacher", EntityKind::Fio)],
+        custom: &[],
+    },
+    Rule {
+        intent: "room.free",
+        required: &[&["свобод*"], &["аудитори*", "кабинет*"]],
+        entities: &[("when", EntityKind::DateTime), ("place", EntityKind::Geo)],
+        custom: &[("building", Custom::Building)],
+    },
+    Rule {
+        intent: "schedule.next",
+        required: &[&["следующ*"], CLASSES],
+        entities: &[],
+        custom: &[],
+    },
+    Rule {
+        intent: "schedule.week",
+        required: &[
+            &["недел*"],
+            &["вся", "всю", "всей", "эта", "эту", "этой", "текущ*"],
+        ],
+        entities: &[],
+        custom: &[],
+    },
+    Rule {
+        intent: "schedule.day",
+        required: &[CLASSES],
+        entities: &[("when", EntityKind::DateTime)],
+        custom: &[("weekday", Custom::Weekday)],
+    },
+];
+
+/// `monday`, the value of the `Weekday` entity from the console
+fn weekday_value(weekday: chrono::Weekday) -> &'static str {
+    match weekday {
+        chrono::Weekday::Mon => "monday",
+        chrono::Weekday::Tue => "tuesday",
+        chrono::Weekday::Wed => "wednesday",
+        chrono::Weekday::Thu => "thursday",
+        chrono::Weekday::Fri => "friday",
+        chrono::Weekday::Sat => "saturday",
+        chrono::Weekday::Sun => "sunday",
+    }
+}
+
+impl Rule {
+    fn recognise(&self, nlu: &Nlu) -> Option<Intent> {
+        let has = |words: &[&str]| {
+            nlu.tokens.iter().any(|token| {
+                words.iter().any(|word| match word.strip_suffix('*') {
+                    Some(stem) => token.starts_with(stem),
+                    None => token == word,
+                })
+            })
+        };
+        if !self.required.iter().all(|words| has(words)) {
+            return None;
+        }
+        let mut slots = HashMap::new();
+        for (name, kind) in self.entities {
+            let entity = nlu
+                .entities
+                .iter()
+                .find(|entity| kind.matches(&entity.named_entity));
+            if let Some(entity) = entity {
+                let slot = Slot {
+                    tokens: Some(entity.tokens.clone()),
+                    value: SlotValue::Entity(entity.named_entity.clone()),
+                };
+                slots.insert(String::from(*name), slot);
+            }
+        }
+        for (name, custom) in self.custom {
+            if let Some((position, value)) = custom.find(&nlu.tokens) {
+                let slot = Slot {
+                    tokens: Some(Token {
+                        start: position.into(),
+                        end: (position + 1).into(),
+                    }),
+                    value: SlotValue::Custom {
+                        entity_type: String::from(custom.entity_type()),
+                        value,
+                    },
+                };
+                slots.insert(String::from(*name), slot);
+            }
+        }
+        Some(Intent { slots })
+    }
+}
+
+/// Intents that the console grammars would recognise in the words and entities of a message
+pub fn recognise(nlu: &Nlu) -> HashMap<String, Intent> {
+    RULES
+        .iter()
+        .filter_map(|rule| Some((String::from(rule.intent), rule.recognise(nlu)?)))
+        .collect()
+}
+
+#[test]
+fn intents_are_recognised() {
+    use serde_json::json;
+
+    let nlu = |tokens: &[&str], entities: Value| -> Nlu {
+        serde_json::from_value(json!({ "tokens": tokens, "entities": entities })).unwrap()
+    };
+    let names = |nlu: &Nlu| {
+        let mut names: Vec<String> = recognise(nlu).into_keys().collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names(&nlu(&["привет"], json!([]))), Vec::<String>::new());
+    assert_eq!(
+        names(&nlu(&["какая", "следующая", "пара"], json!([]))),
+        ["schedule.day", "schedule.next"]
+    );
+    assert_eq!(
+        names(&nlu(&["пары", "на", "всю", "неделю"], json!([]))),
+        ["schedule.day", "schedule.week"]
+    );
+    assert_eq!(
+        names(&nlu(&["сколько", "пар", "на", "этой", "неделе"], json!([]))),
+        ["schedule.day", "schedule.week"]
+    );
+    assert_eq!(
+        names(&nlu(&["расписание", "на", "текущую", "неделю"], json!([]))),
+        ["schedule.week"]
+    );
+    // Short words are matched whole, so other words that start like them aren't taken for them
+    assert_eq!(
+        names(&nlu(&["этаж", "у", "парка", "на", "неделе"], json!([]))),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        names(&nlu(
+            &["сколько", "занятий", "в", "эту", "неделю"],
+            json!([])
+        )),
+        ["schedule.day", "schedule.week"]
+    );
+
+    // Slots are filled as in the console: with the entities and the named weekday
+    let tomorrow = json!({
+        "tokens": { "start": 3, "end": 4 },
+        "type": "YANDEX.DATETIME",
+        "value": { "day": 1, "day_is_relative": true }
+    });
+    let day = &recognise(&nlu(
+        &["когда", "первая", "пара", "завтра"],
+        json!([tomorrow]),
+    ))["schedule.day"];
+    assert_eq!(serde_json::to_value(&day.slots["when"]).unwrap(), tomorrow);
+    let day = &recognise(&nlu(&["пары", "в", "среду"], json!([])))["schedule.day"];
+    assert_eq!(
+        serde_json::to_value(&day.slots["weekday"]).unwrap(),
+        json!({ "tokens": { "start": 2, "end": 3 }, "type": "Weekday", "value": "wednesday" })
+    );
+}
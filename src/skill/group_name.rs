@@ -0,0 +1,372 @@
+use super::speech;
+use crate::GroupInfo;
+
+/// Most candidates named in a clarifying question
+const MAX_CANDIDATES: usize = 3;
+/// Cost of an edit that touches a digit or a separator, more than any allowed distance,
+/// so that a group is never taken for one with another number
+const NUMBER_EDIT: u32 = 3;
+
+/// A known group that resembles what the user said
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    pub group: &'a GroupInfo,
+    /// Number of misrecognised letters
+    pub distance: u32,
+    canonical: String,
+}
+
+/// What the spoken group name turned out to be
+#[derive(Debug)]
+pub enum Match<'a> {
+    Found(&'a GroupInfo),
+    /// Several groups are equally similar, best first
+    Ambiguous(Vec<&'a GroupInfo>),
+    NotFound,
+}
+
+const UNITS: [(&str, u32); 19] = [
+    ("ноль", 0),
+    ("один", 1),
+    ("одна", 1),
+    ("два", 2),
+    ("две", 2),
+    ("три", 3),
+    ("четыре", 4),
+    ("пять", 5),
+    ("шесть", 6),
+    ("семь", 7),
+    ("восемь", 8),
+    ("девять", 9),
+    ("десять", 10),
+    ("одиннадцать", 11),
+    ("двенадцать", 12),
+    ("тринадцать", 13),
+    ("четырнадцать", 14),
+    ("пятнадцать", 15),
+    ("шестнадцать", 16),
+];
+
+const TENS: [(&str, u32); 10] = [
+    ("семнадцать", 17),
+    ("восемнадцать", 18),
+    ("девятнадцать", 19),
+    ("двадцать", 20),
+    ("тридцать", 30),
+    ("сорок", 40),
+    ("пятьдесят", 50),
+    ("шестьдесят", 60),
+    ("семьдесят", 70),
+    ("восемьдесят", 80),
+];
+
+fn lookup<T: Copy>(table: &[(&str, T)], word: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, value)| *value)
+}
+
+/// Latin letters of a transliterated name, e.g. `bivt`, in Cyrillic
+fn transliterate(word: &str) -> String {
+    let mut result = String::new();
+    let mut chars = word.chars().peekable();
+    while let Some(char) = chars.next() {
+        let digraph = chars.peek().and_then(|next| match (char, next) {
+            ('s', 'h') => Some('Ш'),
+            ('c', 'h') => Some('Ч'),
+            ('z', 'h') => Some('Ж'),
+            ('y', 'a') => Some('Я'),
+            ('y', 'u') => Some('Ю'),
+            ('k', 'h') => Some('Х'),
+            _ => None,
+        });
+        if let Some(digraph) = digraph {
+            chars.next();
+            result.push(digraph);
+            continue;
+        }
+        let cyrillic = match char {
+            'a' => 'А',
+            'b' => 'Б',
+            'v' | 'w' => 'В',
+            'g' => 'Г',
+            'd' => 'Д',
+            'e' => 'Е',
+            'z' => 'З',
+            'i' | 'j' => 'И',
+            'k' | 'q' => 'К',
+            'l' => 'Л',
+            'm' => 'М',
+            'n' => 'Н',
+            'o' => 'О',
+            'p' => 'П',
+            'r' => 'Р',
+            's' => 'С',
+            't' => 'Т',
+            'u' => 'У',
+            'f' => 'Ф',
+            'h' | 'x' => 'Х',
+            'c' => 'Ц',
+            'y' => 'Ы',
+            other => other.to_uppercase().next().unwrap_or(other),
+        };
+        result.push(cyrillic);
+    }
+    result
+}
+
+/// A run of letters or a number in a group name
+enum Part {
+    Letters(String),
+    Number(String),
+}
+
+/// Splits a written name into letters and numbers, dropping everything else
+fn parts(text: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&char) = chars.peek() {
+        if char.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+            parts.push(Part::Number(number));
+        } else if char.is_alphabetic() {
+            let mut letters = String::new();
+            while let Some(letter) = chars.next_if(|char| char.is_alphabetic()) {
+                letters.push(letter);
+            }
+            parts.push(Part::Letters(transliterate(&letters.to_lowercase())));
+        } else {
+            chars.next();
+        }
+    }
+    parts
+}
+
+/// The canonical form names are compared in: upper case Cyrillic letters
+/// and numbers separated by dashes, with a dash at the end, e.g. `БИВТ-21-15-`
+fn join(parts: &[Part]) -> String {
+    let mut canonical = String::new();
+    let mut after_letters = false;
+    for part in parts {
+        match part {
+            Part::Letters(letters) => {
+                if !after_letters && !canonical.is_empty() {
+                    canonical.push('-');
+                }
+                canonical.push_str(&letters.replace('Ё', "Е"));
+                after_letters = true;
+            }
+            Part::Number(number) => {
+                if !canonical.is_empty() {
+                    canonical.push('-');
+                }
+                canonical.push_str(number);
+                after_letters = false;
+            }
+        }
+    }
+    canonical.push('-');
+    canonical
+}
+
+fn canonical_name(name: &str) -> String {
+    join(&parts(name))
+}
+
+/// The words of a message in the canonical form of group names.
+/// Numbers said with words become digits and spelled out letters join the word they are in,
+/// e.g. "бэ и вэ тэ двадцать один пятнадцать" becomes `БИВТ-21-15-`
+pub fn canonical_tokens(tokens: &[String]) -> String {
+    let mut spoken = Vec::new();
+    let mut tokens = tokens.iter().map(|token| token.to_lowercase()).peekable();
+    while let Some(token) = tokens.next() {
+        if let Some(tens) = lookup(&TENS, &token) {
+            let units = tokens
+                .peek()
+                .and_then(|next| lookup(&UNITS[1..10], next))
+                .filter(|_| tens % 10 == 0);
+            if units.is_some() {
+                tokens.next();
+            }
+            spoken.push(Part::Number((tens + units.unwrap_or(0)).to_string()));
+        } else if let Some(number) = lookup(&UNITS, &token) {
+            spoken.push(Part::Number(number.to_string()));
+        } else if let Some(letter) = speech::named_letter(&token) {
+            spoken.push(Part::Letters(letter.to_string()));
+        } else {
+            spoken.extend(parts(&token));
+        }
+    }
+    join(&spoken)
+}
+
+/// How many letters may be misrecognised in a name
+fn allowed_distance(canonical: &str) -> u32 {
+    let letters = canonical
+        .chars()
+        .filter(|char| char.is_alphabetic())
+        .count();
+    (letters / 3).min(2) as u32
+}
+
+fn edit_cost(char: char) -> u32 {
+    if char.is_alphabetic() {
+        1
+    } else {
+        NUMBER_EDIT
+    }
+}
+
+/// The fewest edits that turn the name into some part of the spoken text
+fn substring_distance(name: &str, spoken: &str) -> u32 {
+    let name: Vec<char> = name.chars().collect();
+    let spoken: Vec<char> = spoken.chars().collect();
+    // The name may start anywhere in the spoken text for free
+    let mut previous = vec![0; spoken.len() + 1];
+    for &name_char in &name {
+        let mut current = Vec::with_capacity(spoken.len() + 1);
+        current.push(previous[0] + edit_cost(name_char));
+        for (index, &spoken_char) in spoken.iter().enumerate() {
+            let substitution = if name_char == spoken_char {
+                0
+            } else {
+                edit_cost(name_char).max(edit_cost(spoken_char))
+            };
+            let cost = (previous[index] + substitution)
+                .min(previous[index + 1] + edit_cost(name_char))
+                .min(current[index] + edit_cost(spoken_char));
+            current.push(cost);
+        }
+        previous = current;
+    }
+    previous.into_iter().min().unwrap_or(0)
+}
+
+/// Groups that resemble the spoken name, the most similar first.
+/// Among equally similar groups the longer names go first, as they are more specific
+pub fn rank<'a>(
+    groups: impl IntoIterator<Item = &'a GroupInfo>,
+    tokens: &[String],
+) -> Vec<Candidate<'a>> {
+    let spoken = canonical_tokens(tokens);
+    let mut candidates: Vec<Candidate> = groups
+        .into_iter()
+        .filter_map(|group| {
+            let canonical = canonical_name(&group.name);
+            if canonical.len() <= 1 {
+                return None;
+            }
+            let distance = substring_distance(&canonical, &spoken);
+            (distance <= allowed_distance(&canonical)).then_some(Candidate {
+                group,
+                distance,
+                canonical,
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|candidate| {
+        (
+            candidate.distance,
+            std::cmp::Reverse(candidate.canonical.len()),
+        )
+    });
+    candidates
+}
+
+/// The group the user named, if only one of the known ones resembles it the most.
+/// A name that is a part of a longer equally similar one, like `ПМ-21-1` in `БПМ-21-1`,
+/// doesn't make the match ambiguous
+pub fn find<'a>(groups: impl IntoIterator<Item = &'a GroupInfo>, tokens: &[String]) -> Match<'a> {
+    let candidates = rank(groups, tokens);
+    let Some(best) = candidates.first().map(|candidate| candidate.distance) else {
+        return Match::NotFound;
+    };
+    let best: Vec<&Candidate> = candidates
+        .iter()
+        .take_while(|candidate| candidate.distance == best)
+        .collect();
+    let mut groups: Vec<&GroupInfo> = best
+        .iter()
+        .filter(|candidate| {
+            !best.iter().any(|other| {
+                other.canonical.len() > candidate.canonical.len()
+                    && other.canonical.contains(&candidate.canonical)
+            })
+        })
+        .map(|candidate| candidate.group)
+        .collect();
+    if groups.len() == 1 {
+        Match::Found(groups[0])
+    } else {
+        groups.truncate(MAX_CANDIDATES);
+        Match::Ambiguous(groups)
+    }
+}
+
+/// Asks which of the similar groups the user meant,
+/// e.g. `Вы имели в виду БИВТ-21-15 или БИВ-21-15?`
+pub fn clarification(groups: &[&GroupInfo]) -> String {
+    let names: Vec<&str> = groups.iter().map(|group| group.name.as_str()).collect();
+    let names = match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} или {last}", rest.join(", ")),
+        _ => names.concat(),
+    };
+    format!("Вы имели в виду {names}? Назовите группу ещё раз.")
+}
+
+#[test]
+fn spoken_names_are_normalised() {
+    let tokens = |text: &str| -> Vec<String> { text.split(' ').map(String::from).collect() };
+    assert_eq!(canonical_name("БИВТ-21-15"), "БИВТ-21-15-");
+    assert_eq!(canonical_name("БПМ-21-1 (ИТКН)"), "БПМ-21-1-ИТКН-");
+    assert_eq!(
+        canonical_tokens(&tokens("бивт двадцать один пятнадцать")),
+        "БИВТ-21-15-"
+    );
+    assert_eq!(canonical_tokens(&tokens("бэ и вэ тэ 21 15")), "БИВТ-21-15-");
+    assert_eq!(canonical_tokens(&tokens("bivt 21 15")), "БИВТ-21-15-");
+    assert_eq!(canonical_tokens(&tokens("БИВТ-21-15")), "БИВТ-21-15-");
+    assert_eq!(canonical_tokens(&tokens("семнадцать два")), "17-2-");
+}
+
+#[test]
+fn spoken_names_are_matched() {
+    let tokens = |text: &str| -> Vec<String> { text.split(' ').map(String::from).collect() };
+    let group = |name: &str| GroupInfo::from_weeks(String::from(name), Vec::new());
+    let groups = [
+        group("БИВТ-21-15"),
+        group("БИВТ-21-1"),
+        group("БИВ-21-15"),
+        group("ПМ-21-1"),
+        group("БПМ-21-1"),
+    ];
+    let found = |text: &str| match find(&groups, &tokens(text)) {
+        Match::Found(group) => Some(group.name.as_str()),
+        _ => None,
+    };
+
+    assert_eq!(found("я из группы бивт 21 15"), Some("БИВТ-21-15"));
+    assert_eq!(found("бивт двадцать один один"), Some("БИВТ-21-1"));
+    assert_eq!(found("bivt 21 15 подгруппа 2"), Some("БИВТ-21-15"));
+    assert_eq!(found("бпм 21 1"), Some("БПМ-21-1"));
+    assert_eq!(found("пм 21 1"), Some("ПМ-21-1"));
+    // A misrecognised letter still finds the group, a misrecognised number doesn't
+    assert_eq!(found("пивт 21 1"), Some("БИВТ-21-1"));
+    assert_eq!(found("бивт 21 16"), None);
+    assert!(matches!(
+        find(&groups, &tokens("бпм 22 1")),
+        Match::NotFound
+    ));
+
+    let Match::Ambiguous(similar) = find(&groups, &tokens("бивд 21 15")) else {
+        panic!("one letter away from two groups");
+    };
+    assert_eq!(
+        clarification(&similar),
+        "Вы имели в виду БИВТ-21-15 или БИВ-21-15? Назовите группу ещё раз."
+    );
+}
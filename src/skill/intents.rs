@@ -0,0 +1,172 @@
+use super::{grammar, requested_date, rooms, schedule, teachers, Context, Question};
+use crate::alisa::{
+    next_weekday, Intent, Nlu, Slot, WebhookRequest, WebhookResponse, YandexEnteties,
+};
+use serde::Deserialize;
+use std::{borrow::Cow, collections::HashMap};
+
+/// What the skill does when Dialogs recognise an intent, [`None`] if it can't answer it
+pub type Handler = fn(&Intent, &WebhookRequest, &Context) -> Option<WebhookResponse>;
+
+/// Intents defined in the skill's console and their handlers, tried in this order
+pub const REGISTRY: &[(&str, Handler)] = &[
+    ("teacher.where", teachers::teacher_where),
+    ("room.free", rooms::room_free),
+    ("schedule.next", schedule_next),
+    ("schedule.week", schedule_week),
+    ("schedule.day", schedule_day),
+];
+
+/// The intents that Dialogs recognised, if there is a registered one among them.
+/// Otherwise, e.g. if only `YANDEX.HELP` came, the intents are recognised with the local grammar
+fn recognised(nlu: &Nlu) -> Cow<'_, HashMap<String, Intent>> {
+    let registered = REGISTRY
+        .iter()
+        .any(|(name, _)| nlu.intents.contains_key(*name));
+    if registered {
+        Cow::Borrowed(&nlu.intents)
+    } else {
+        Cow::Owned(grammar::recognise(nlu))
+    }
+}
+
+/// The message asks something a registered intent answers
+pub fn is_recognised(nlu: &Nlu) -> bool {
+    let intents = recognised(nlu);
+    REGISTRY.iter().any(|(name, _)| intents.contains_key(*name))
+}
+
+/// Answers with the handler of the first registered intent that was recognised, see [`recognised`]
+pub fn dispatch(request: &WebhookRequest, context: &Context) -> Option<WebhookResponse> {
+    let intents = recognised(&request.request.nlu);
+    REGISTRY
+        .iter()
+        .find_map(|(name, handler)| handler(intents.get(*name)?, request, context))
+}
+
+/// `Weekday` entity of the console, e.g. "в среду"
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<Weekday> for chrono::Weekday {
+    fn from(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Monday => Self::Mon,
+            Weekday::Tuesday => Self::Tue,
+            Weekday::Wednesday => Self::Wed,
+            Weekday::Thursday => Self::Thu,
+            Weekday::Friday => Self::Fri,
+            Weekday::Saturday => Self::Sat,
+            Weekday::Sunday => Self::Sun,
+        }
+    }
+}
+
+/// `schedule.day`: "когда первая пара {when}" with a `when` date or a `weekday`
+fn schedule_day(
+    intent: &Intent,
+    request: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    let from_when = intent
+        .slot("when")
+        .and_then(Slot::entity)
+        .and_then(|entity| match entity {
+            YandexEnteties::DateTime(date_time) => date_time.resolve(context.now),
+            _ => None,
+        })
+        .map(|resolved| resolved.date());
+    let from_weekday = || {
+        let weekday: Weekday = intent.slot("weekday")?.custom()?;
+        next_weekday(context.now.date_naive(), weekday.into())
+    };
+    let date = from_when
+        .or_else(from_weekday)
+        .unwrap_or_else(|| requested_date(request, context.now));
+    Some(schedule(request, context, Question::Day(date)))
+}
+
+/// `schedule.next`: "какая следующая пара"
+fn schedule_next(
+    _: &Intent,
+    request: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    Some(schedule(request, context, Question::NextClass))
+}
+
+/// `schedule.week`: "сколько пар на этой неделе"
+fn schedule_week(
+    _: &Intent,
+    request: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    Some(schedule(request, context, Question::Week))
+}
+
+#[test]
+fn intents_are_dispatched() {
+    use super::{handle, test_config, test_context, test_request, test_schedules};
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let config = test_config();
+    // Sunday, the 4th of September, the next week is a lower one
+    let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+    // The words alone aren't a question about the schedule, only the console intents are
+    let ask = |intents: serde_json::Value| {
+        let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+        let mut request = test_request(&["а", "что", "там"], json!([]), state);
+        request.request.nlu.intents = serde_json::from_value(intents).unwrap();
+        handle(&request, &context).response.text
+    };
+
+    let monday = "Завтра первая пара в 10:50 — Математика, лекция, аудитория Б-436.";
+    let weekday = json!({
+        "schedule.day": {
+            "slots": { "weekday": { "type": "Weekday", "value": "monday" } }
+        }
+    });
+    assert_eq!(ask(weekday), monday);
+    let when = json!({
+        "schedule.day": {
+            "slots": {
+                "when": {
+                    "type": "YANDEX.DATETIME",
+                    "tokens": { "start": 2, "end": 3 },
+                    "value": { "day": 2, "day_is_relative": true }
+                }
+            }
+        }
+    });
+    assert_eq!(ask(when), "Послезавтра у вас нет пар.");
+    assert_eq!(ask(json!({ "schedule.day": {} })), "Сегодня у вас нет пар.");
+    // `schedule.next` is tried before `schedule.day`
+    assert_eq!(
+        ask(json!({ "schedule.day": {}, "schedule.next": {} })),
+        "Следующая пара завтра в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+    assert_eq!(
+        ask(json!({ "schedule.week": {} })),
+        "На этой неделе у вас нет пар."
+    );
+    assert_eq!(ask(json!({ "unknown.intent": {} })), super::NOT_UNDERSTOOD);
+
+    // The built-in intents that have no handlers don't stop the words from being understood
+    let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+    let mut request = test_request(&["какая", "следующая", "пара"], json!([]), state);
+    request.request.nlu.intents = serde_json::from_value(json!({ "YANDEX.REJECT": {} })).unwrap();
+    assert_eq!(
+        handle(&request, &context).response.text,
+        "Следующая пара завтра в 10:50 — Математика, лекция, аудитория Б-436."
+    );
+}
@@ -0,0 +1,303 @@
+use super::{
+    dialog::{self, Awaiting},
+    group_name::{self, Match},
+    Context, UserGroup,
+};
+use crate::alisa::{Nlu, Response, WebhookRequest, WebhookResponse, YandexEnteties, YandexNumber};
+use chrono::{DateTime, FixedOffset};
+use serde_json::json;
+
+const ASK_GROUP: &str = "Назовите вашу группу, например, БИВТ-21-15.";
+const GROUP_NOT_FOUND: &str = "Я не нашла такую группу. \
+    Назовите её ещё раз, например, БИВТ-21-15.";
+
+/// Requests to forget the group, e.g. "смени группу"
+pub fn is_change_request(tokens: &[String]) -> bool {
+    let change = [
+        "смени",
+        "сменить",
+        "поменяй",
+        "поменять",
+        "измени",
+        "изменить",
+    ];
+    tokens.iter().any(|token| change.contains(&token.as_str())) && mentions_group(tokens)
+}
+
+/// The user talks about their group, e.g. "я из группы бивт 21 15"
+pub fn mentions_group(tokens: &[String]) -> bool {
+    tokens.iter().any(|token| token.starts_with("групп"))
+}
+
+/// Some of the words may be a group's name, as all the names have numbers in them
+pub fn may_name_group(tokens: &[String]) -> bool {
+    group_name::canonical_tokens(tokens)
+        .chars()
+        .any(|char| char.is_ascii_digit())
+}
+
+/// A number said either with digits or as an ordinal, e.g. "вторая"
+fn ordinal(token: &str) -> Option<u8> {
+    if let Ok(number) = token.parse() {
+        return Some(number);
+    }
+    [("перв", 1), ("втор", 2), ("трет", 3), ("четв", 4)]
+        .into_iter()
+        .find(|(stem, _)| token.starts_with(stem))
+        .map(|(_, number)| number)
+}
+
+/// Number of the subgroup, named next to the word "подгруппа"
+/// If `any_number` is taken, e.g. when the skill asked for the subgroup, any number in the message is
+fn subgroup_number(nlu: &Nlu, any_number: bool) -> Option<u8> {
+    let number_at = |position: usize| {
+        let from_entity = nlu.entities.iter().find_map(|entity| {
+            let YandexEnteties::Number(YandexNumber::Integer(number)) = entity.named_entity else {
+                return None;
+            };
+            let start = entity.tokens.start.as_u64()?;
+            let end = entity.tokens.end.as_u64()?;
+            (start..end)
+                .contains(&(position as u64))
+                .then(|| u8::try_from(number).ok())
+                .flatten()
+        });
+        from_entity.or_else(|| ordinal(nlu.tokens.get(position)?))
+    };
+    let keyword = nlu
+        .tokens
+        .iter()
+        .position(|token| token.starts_with("подгрупп"));
+    match keyword {
+        Some(keyword) => number_at(keyword + 1).or_else(|| number_at(keyword.checked_sub(1)?)),
+        None if any_number => (0..nlu.tokens.len()).find_map(number_at),
+        None => None,
+    }
+}
+
+/// `1 или 2`, `1, 2 или 3`
+fn list_subgroups(subgroups: &[u8]) -> String {
+    let numbers: Vec<String> = subgroups.iter().map(u8::to_string).collect();
+    match numbers.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} или {last}", rest.join(", ")),
+        _ => numbers.concat(),
+    }
+}
+
+/// Stores the group in the user state of a logged in user, or in the state of the application.
+/// [`None`] forgets the group in both, as a logged in user may have it saved before logging in
+pub fn remember(
+    response: &mut WebhookResponse,
+    request: &WebhookRequest,
+    group: Option<&UserGroup>,
+) {
+    let Some(group) = group else {
+        let forgotten = json!({ "group": null, "subgroup": null });
+        if request.session.user.is_some() {
+            response.user_state_update = Some(forgotten.clone());
+        }
+        response.application_state = Some(forgotten);
+        return;
+    };
+    let state = serde_json::to_value(group).unwrap();
+    if request.session.user.is_some() {
+        response.user_state_update = Some(state);
+    } else {
+        response.application_state = Some(state);
+    }
+}
+
+/// Asks for the group and waits for it in the next message
+pub fn ask_group(text: &str, now: DateTime<FixedOffset>) -> WebhookResponse {
+    dialog::ask(Response::new(text), Awaiting::Group, now)
+}
+
+/// Forgets the group and asks for a new one
+pub fn change_group(request: &WebhookRequest, now: DateTime<FixedOffset>) -> WebhookResponse {
+    let mut response = ask_group(&format!("Хорошо. {ASK_GROUP}"), now);
+    remember(&mut response, request, None);
+    response
+}
+
+/// Takes the group and the subgroup from a message, asking for whatever is missing
+pub fn capture(
+    request: &WebhookRequest,
+    context: &Context,
+    awaiting: Option<Awaiting>,
+) -> WebhookResponse {
+    let schedules = context.schedules;
+    let now = context.now;
+    let nlu = &request.request.nlu;
+    let mut named_group = true;
+    let group = match group_name::find(schedules.groups(), &nlu.tokens) {
+        Match::Found(group) => group,
+        Match::Ambiguous(similar) => return ask_group(&group_name::clarification(&similar), now),
+        Match::NotFound => match &awaiting {
+            Some(Awaiting::Subgroup { group }) => match schedules.find_group(group) {
+                Some(group) => {
+                    named_group = false;
+                    group
+                }
+                None => return ask_group(GROUP_NOT_FOUND, now),
+            },
+            _ => return ask_group(GROUP_NOT_FOUND, now),
+        },
+    };
+    let subgroups: Vec<u8> = group
+        .weeks()
+        .into_iter()
+        .filter_map(|(number, _)| number)
+        .collect();
+
+    // The numbers of the group's name aren't the subgroup
+    let any_number = matches!(awaiting, Some(Awaiting::Subgroup { .. })) && !named_group;
+    let subgroup = if subgroups.is_empty() {
+        None
+    } else {
+        let ask = |text: String| {
+            let awaiting = Awaiting::Subgroup {
+                group: group.name.clone(),
+            };
+            dialog::ask(Response::new(text), awaiting, now)
+        };
+        match subgroup_number(nlu, any_number) {
+            Some(number) if subgroups.contains(&number) => Some(number),
+            Some(_) => {
+                return ask(format!(
+                    "В группе {} есть только подгруппы {}. В какой вы?",
+                    group.name,
+                    list_subgroups(&subgroups)
+                ))
+            }
+            None => {
+                return ask(format!(
+                    "В какой подгруппе группы {} вы учитесь: {}?",
+                    group.name,
+                    list_subgroups(&subgroups)
+                ))
+            }
+        }
+    };
+
+    let user_group = UserGroup {
+        group: group.name.clone(),
+        subgroup,
+    };
+    let text = match subgroup {
+        Some(subgroup) => format!("Запомнила: группа {}, подгруппа {subgroup}.", group.name),
+        None => format!("Запомнила: группа {}.", group.name),
+    };
+    let mut response = WebhookResponse::new(Response::new(format!(
+        "{text} Теперь спросите, например, когда завтра первая пара."
+    )));
+    remember(&mut response, request, Some(&user_group));
+    response
+}
+
+#[test]
+fn group_is_remembered() {
+    use super::{handle, test_config, test_context, test_request, test_schedules};
+
+    let schedules = test_schedules();
+    let config = test_config();
+    let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+    let number = |position: u64, value: u8| {
+        json!({
+            "tokens": { "start": position, "end": position + 1 },
+            "type": "YANDEX.NUMBER",
+            "value": value
+        })
+    };
+
+    // Everything at once
+    let tokens = ["я", "из", "группы", "бивт", "21", "15", "подгруппа", "2"];
+    let entities = json!([number(4, 21), number(5, 15), number(7, 2)]);
+    let response = handle(&test_request(&tokens, entities, json!({})), &context);
+    assert_eq!(
+        response.application_state,
+        Some(json!({ "group": "БИВТ-21-15", "subgroup": 2 }))
+    );
+    assert_eq!(response.session_state, None);
+
+    // The subgroup is asked for, then taken from the next message
+    let tokens = ["моя", "группа", "бивт", "21", "15"];
+    let response = handle(&test_request(&tokens, json!([]), json!({})), &context);
+    assert_eq!(
+        response.response.text,
+        "В какой подгруппе группы БИВТ-21-15 вы учитесь: 1 или 2?"
+    );
+    let session = response.session_state.unwrap();
+    assert_eq!(
+        session,
+        json!({
+            "awaiting": "subgroup",
+            "group": "БИВТ-21-15",
+            "asked": "2022-09-04T20:00:00+03:00"
+        })
+    );
+    let state = json!({ "session": session });
+    // The group is named again, its numbers aren't the subgroup
+    let tokens = ["бивт", "21", "15"];
+    let entities = json!([number(1, 21), number(2, 15)]);
+    let response = handle(&test_request(&tokens, entities, state.clone()), &context);
+    assert_eq!(
+        response.response.text,
+        "В какой подгруппе группы БИВТ-21-15 вы учитесь: 1 или 2?"
+    );
+    let response = handle(
+        &test_request(&["третья"], json!([]), state.clone()),
+        &context,
+    );
+    assert_eq!(
+        response.response.text,
+        "В группе БИВТ-21-15 есть только подгруппы 1 или 2. В какой вы?"
+    );
+    let response = handle(&test_request(&["первая"], json!([]), state), &context);
+    assert_eq!(
+        response.application_state,
+        Some(json!({ "group": "БИВТ-21-15", "subgroup": 1 }))
+    );
+
+    // A group without subgroups, after asking to change the group
+    let saved = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+    let response = handle(
+        &test_request(&["смени", "группу"], json!([]), saved),
+        &context,
+    );
+    assert_eq!(
+        response.application_state,
+        Some(json!({ "group": null, "subgroup": null }))
+    );
+    let state = json!({ "session": response.session_state.unwrap() });
+    let response = handle(
+        &test_request(&["бпм", "21", "1"], json!([]), state.clone()),
+        &context,
+    );
+    assert_eq!(
+        response.response.text.split('.').next(),
+        Some("Запомнила: группа БПМ-21-1")
+    );
+    let response = handle(
+        &test_request(&["бпм", "22", "1"], json!([]), state),
+        &context,
+    );
+    assert_eq!(response.response.text, GROUP_NOT_FOUND);
+}
+
+#[test]
+fn logged_in_users_forget_the_group_everywhere() {
+    use super::{handle, test_config, test_context, test_request, test_schedules};
+
+    let schedules = test_schedules();
+    let config = test_config();
+    let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+    // Saved in the application before the user logged in
+    let saved = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+    let mut request = test_request(&["смени", "группу"], json!([]), saved);
+    request.session.user =
+        serde_json::from_value(json!({ "user_id": "6C91DA5198D1758C" })).unwrap();
+    let response = handle(&request, &context);
+    let forgotten = Some(json!({ "group": null, "subgroup": null }));
+    assert_eq!(response.user_state_update, forgotten);
+    assert_eq!(response.application_state, forgotten);
+}
@@ -0,0 +1,252 @@
+use super::{day_name, speech, Context};
+use crate::{
+    alisa::{
+        Geolocation, Intent, Resolved, Response, Slot, WebhookRequest, WebhookResponse,
+        YandexEnteties,
+    },
+    calendar,
+};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+/// Most rooms read out in one answer
+const MAX_ROOMS: usize = 5;
+
+/// A room that is free during a lesson
+#[derive(Debug, PartialEq, Eq)]
+struct FreeRoom<'a> {
+    room: &'a str,
+    /// The lesson it gets occupied at, [`None`] if it stays free till the end of the day
+    until: Option<u8>,
+}
+
+/// The letter of the building from the `building` slot, or the last letter
+/// of a house number from the `place` slot, e.g. `Б` in "дом 4б"
+fn building(intent: &Intent) -> Option<String> {
+    if let Some(building) = intent.slot("building").and_then(Slot::custom::<String>) {
+        return Some(building.to_uppercase());
+    }
+    let Some(YandexEnteties::Geo(Geolocation::House {
+        house_number: Some(house_number),
+        ..
+    })) = intent.slot("place").and_then(Slot::entity)
+    else {
+        return None;
+    };
+    house_number
+        .chars()
+        .rev()
+        .find(|char| char.is_alphabetic())
+        .map(|letter| letter.to_uppercase().collect())
+}
+
+/// The moment from the `when` slot, and whether it was named at all
+fn moment(intent: &Intent, context: &Context) -> (NaiveDate, NaiveTime, bool) {
+    let now = context.now.naive_local();
+    let resolved = intent
+        .slot("when")
+        .and_then(Slot::entity)
+        .and_then(|entity| match entity {
+            YandexEnteties::DateTime(date_time) => date_time.resolve(context.now),
+            _ => None,
+        });
+    match resolved {
+        Some(Resolved::DateTime(date_time)) => (date_time.date(), date_time.time(), true),
+        Some(resolved) if resolved.date() != now.date() => {
+            (resolved.date(), calendar::lesson_start(0), true)
+        }
+        _ => (now.date(), now.time(), false),
+    }
+}
+
+/// Rooms of the building that are free during the lesson,
+/// the ones that stay free for longer first
+fn free_rooms<'a>(
+    context: &'a Context,
+    building: Option<&str>,
+    date: NaiveDate,
+    lesson: u8,
+) -> Vec<FreeRoom<'a>> {
+    let day = date.weekday().num_days_from_monday() as u8;
+    let parity = calendar::week_parity(context.config.semester_start(date), date);
+    let in_building = |room: &str| {
+        building.is_none_or(|building| {
+            room.split('-')
+                .next()
+                .is_some_and(|prefix| prefix.to_uppercase() == building)
+        })
+    };
+    let mut free: Vec<FreeRoom> = context
+        .schedules
+        .day_occupancy(day, parity)
+        .into_iter()
+        .filter(|(room, lessons)| in_building(room) && !lessons.contains(&lesson))
+        .map(|(room, lessons)| FreeRoom {
+            room,
+            until: lessons.range(lesson..).next().copied(),
+        })
+        .collect();
+    // Stable, so the rooms stay sorted by their names
+    free.sort_by_key(|room| std::cmp::Reverse(room.until.unwrap_or(u8::MAX)));
+    free
+}
+
+/// `room.free`: "есть ли свободная аудитория в корпусе Б в 12"
+pub fn room_free(
+    intent: &Intent,
+    _: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    let building = building(intent);
+    let (date, time, named) = moment(intent, context);
+    let place = building
+        .as_ref()
+        .map_or_else(String::new, |building| format!(" в корпусе {building}"));
+    let when = if named {
+        format!(
+            "{} в {}",
+            day_name(date, context.now.date_naive()),
+            calendar::format_time(time)
+        )
+    } else {
+        String::from("Сейчас")
+    };
+    let Some(lesson) = calendar::lesson_at(time) else {
+        return Some(WebhookResponse::new(Response::new(format!(
+            "{when} пары уже закончились, все аудитории{place} свободны."
+        ))));
+    };
+    let free = free_rooms(context, building.as_deref(), date, lesson);
+    if free.is_empty() {
+        return Some(WebhookResponse::new(Response::new(format!(
+            "{when}{place} нет свободных аудиторий."
+        ))));
+    }
+    let until = |room: &FreeRoom| {
+        room.until.map_or_else(
+            || String::from("до конца дня"),
+            |lesson| {
+                format!(
+                    "до {}",
+                    calendar::format_time(calendar::lesson_start(lesson))
+                )
+            },
+        )
+    };
+    let listed = &free[..free.len().min(MAX_ROOMS)];
+    let more = match free.len() - listed.len() {
+        0 => String::new(),
+        more => format!(" И ещё {more}."),
+    };
+    let text = listed
+        .iter()
+        .map(|room| format!("{} {}", room.room, until(room)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let tts = listed
+        .iter()
+        .map(|room| format!("{} {}", speech::room(room.room), until(room)))
+        .collect::<Vec<_>>()
+        .join(&format!(", {} ", speech::PAUSE));
+    Some(WebhookResponse::new(Response {
+        tts: Some(format!("{when}{place} свободны: {tts}.{more}")),
+        ..Response::new(format!("{when}{place} свободны: {text}.{more}"))
+    }))
+}
+
+#[test]
+fn free_rooms_are_found() {
+    use super::{handle, test_config, test_context, test_course, test_request};
+    use crate::{
+        store::{set_class, Slot, WeekParity},
+        Class, ClassType, GroupInfo, Week,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    let occupy = |week: &mut Week, room: &str, lesson, parity| {
+        let slot = Slot {
+            day: 0,
+            lesson,
+            week: parity,
+        };
+        let class = Class {
+            name: Arc::from("Математика"),
+            class_type: ClassType::Lection,
+            teacher: None,
+            room: Arc::from(room),
+        };
+        set_class(week, slot, class);
+    };
+    let mut week = Week::default();
+    occupy(&mut week, "Б-436", 1, WeekParity::Lower);
+    occupy(&mut week, "Б-436", 3, WeekParity::Lower);
+    // Not rooms, so never free
+    occupy(&mut week, "Спортзал", 2, WeekParity::Lower);
+    occupy(&mut week, "ЛМС", 2, WeekParity::Lower);
+    // Upper weeks don't matter on a lower one
+    let mut upper = Week::default();
+    occupy(&mut upper, "Б-501", 2, WeekParity::Upper);
+    let mut evening = Week::default();
+    occupy(&mut evening, "Л-550", 0, WeekParity::Lower);
+    let schedules = test_course(vec![
+        GroupInfo::from_weeks(String::from("БИВТ-21-15"), vec![(None, week)]),
+        GroupInfo::from_weeks(String::from("БИВТ-21-16"), vec![(None, upper)]),
+        GroupInfo::from_weeks(String::from("БИВТ-21-17"), vec![(None, evening)]),
+    ]);
+    let config = test_config();
+    let ask = |now: &str, tokens: &[&str], entities: serde_json::Value| {
+        let context = test_context(&schedules, &config, now);
+        handle(&test_request(tokens, entities, json!({})), &context).response
+    };
+    // Monday of a lower week, during the first lesson
+    let morning = "2022-09-05T09:30:00+03:00";
+
+    let response = ask(
+        morning,
+        &["есть", "свободная", "аудитория", "в", "корпусе", "бэ"],
+        json!([]),
+    );
+    assert_eq!(
+        response.text,
+        "Сейчас в корпусе Б свободны: Б-501 до конца дня, Б-436 до 10:50."
+    );
+    assert_eq!(
+        response.tts.as_deref(),
+        Some(
+            "Сейчас в корпусе Б свободны: бэ пятьсот один до конца дня, sil <[300]> \
+            бэ четыреста тридцать шесть до 10:50."
+        )
+    );
+    assert_eq!(
+        ask(morning, &["свободные", "аудитории"], json!([])).text,
+        "Сейчас свободны: Б-501 до конца дня, Б-436 до 10:50."
+    );
+
+    // At the named time, in a building from a house number
+    let entities = json!([
+        {
+            "tokens": { "start": 2, "end": 4 },
+            "type": "YANDEX.DATETIME",
+            "value": { "hour": 11, "minute": 0 }
+        },
+        {
+            "tokens": { "start": 5, "end": 7 },
+            "type": "YANDEX.GEO",
+            "value": { "street": "ленинский проспект", "house_number": "4б" }
+        }
+    ]);
+    let tokens = ["свободный", "кабинет", "в", "11", "на", "ленинском", "4б"];
+    assert_eq!(
+        ask(morning, &tokens, entities).text,
+        "Сегодня в 11:00 в корпусе Б свободны: Б-501 до конца дня."
+    );
+    assert_eq!(
+        ask(
+            "2022-09-05T21:30:00+03:00",
+            &["свободные", "аудитории"],
+            json!([])
+        )
+        .text,
+        "Сейчас пары уже закончились, все аудитории свободны."
+    );
+}
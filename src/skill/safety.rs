@@ -0,0 +1,279 @@
+use crate::alisa::{Nlu, WebhookRequest, YandexEnteties};
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::hash_map::RandomState,
+    fs::OpenOptions,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    path::Path,
+    sync::OnceLock,
+};
+
+/// Said instead of an answer to the dangerous messages
+pub const DANGEROUS_REPLY: &str = "Я не понимаю, о чём вы. \
+    Пожалуйста, переформулируйте вопрос.";
+
+/// Beginnings of the obscene words, a token that starts with one of them is profane
+const PROFANE_STEMS: &[&str] = &[
+    "хуй",
+    "хуе",
+    "хуя",
+    "хую",
+    "нахуй",
+    "похуй",
+    "охуе",
+    "ахуе",
+    "пизд",
+    "спизд",
+    "распизд",
+    "ебат",
+    "ебан",
+    "ебал",
+    "ебуч",
+    "ебло",
+    "заеб",
+    "наеб",
+    "отъеб",
+    "выеб",
+    "уеб",
+    "долбоеб",
+    "бляд",
+    "блят",
+    "мудак",
+    "мудил",
+    "пидор",
+    "пидар",
+    "гандон",
+    "залуп",
+    "шлюх",
+];
+
+/// Obscene words that begin some decent ones too, so only whole tokens are matched
+const PROFANE_WORDS: &[&str] = &["бля", "сука", "суки", "суку", "сукой", "манда"];
+
+/// Why a message isn't answered
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// Dialogs marked it with `dangerous_context`
+    DangerousContext,
+    /// It has some of the obscene words
+    Profanity,
+}
+
+/// A dangerous message without anything that tells who sent it or what was said
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct LogEntry {
+    /// When the message came, in RFC 3339
+    pub time: String,
+    pub reason: Reason,
+    /// SHA-256 of the application id, so that messages of one user can be grouped
+    pub application: String,
+    /// Salted SHA-256 of the command, so that the same messages can be grouped
+    pub command_hash: String,
+    /// Kinds of the names, places, dates and numbers Dialogs found in the message
+    pub entities: Vec<&'static str>,
+}
+
+/// Kinds of the entities of a message, in the order they were said in
+fn entity_kinds(nlu: &Nlu) -> Vec<&'static str> {
+    let mut entities: Vec<_> = nlu.entities.iter().collect();
+    entities.sort_by_key(|entity| entity.tokens.start.as_u64());
+    entities
+        .into_iter()
+        .map(|entity| match entity.named_entity {
+            YandexEnteties::Fio(_) => "имя",
+            YandexEnteties::Geo(_) => "место",
+            YandexEnteties::Number(_) => "число",
+            YandexEnteties::DateTime(_) => "дата",
+        })
+        .collect()
+}
+
+/// Mixed into the hashes of the commands, so that the short ones can't be guessed.
+/// A new one is made on every start, the same messages are only grouped until a restart
+fn salt() -> u64 {
+    static SALT: OnceLock<u64> = OnceLock::new();
+    *SALT.get_or_init(|| RandomState::new().build_hasher().finish())
+}
+
+impl LogEntry {
+    pub fn new(request: &WebhookRequest, reason: Reason, time: DateTime<FixedOffset>) -> Self {
+        let application = Sha256::digest(request.session.application.application_id.as_bytes());
+        let command = Sha256::new()
+            .chain_update(salt().to_le_bytes())
+            .chain_update(request.request.command.as_bytes())
+            .finalize();
+        Self {
+            time: time.to_rfc3339(),
+            reason,
+            application: hex::encode(application),
+            command_hash: hex::encode(command),
+            entities: entity_kinds(&request.request.nlu),
+        }
+    }
+}
+
+/// The message has some of the obscene words
+fn is_profane(nlu: &Nlu) -> bool {
+    nlu.tokens.iter().any(|token| {
+        let token = token.to_lowercase().replace('ё', "е");
+        PROFANE_WORDS.contains(&token.as_str())
+            || PROFANE_STEMS.iter().any(|stem| token.starts_with(stem))
+    })
+}
+
+/// Dialogs think that the message contains threats, hate speech or talks about suicide,
+/// or it is profane, which Dialogs don't mark
+pub fn is_dangerous(request: &WebhookRequest) -> Option<Reason> {
+    let marked = request
+        .request
+        .markup
+        .as_ref()
+        .is_some_and(|markup| markup.dangerous_context);
+    if marked {
+        Some(Reason::DangerousContext)
+    } else if is_profane(&request.request.nlu) {
+        Some(Reason::Profanity)
+    } else {
+        None
+    }
+}
+
+/// Appends the message to a JSON lines log at `path`, or prints it to stderr
+pub fn log(entry: &LogEntry, path: Option<&Path>) {
+    let line = serde_json::to_string(entry).unwrap();
+    let Some(path) = path else {
+        eprintln!("Dangerous context: {line}");
+        return;
+    };
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(error) = written {
+        eprintln!(
+            "Couldn't log a dangerous message to {}: {error}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn dangerous_messages_are_answered_and_logged() {
+    use super::{handle, test_config, test_context, test_request, test_schedules, Config};
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let log = std::env::temp_dir().join(format!("misisa-dangerous-{}.jsonl", std::process::id()));
+    let config = Config {
+        dangerous_reply: Some(String::from("Давайте поговорим о расписании.")),
+        dangerous_log: Some(log.clone()),
+        ..test_config()
+    };
+    let context = test_context(&schedules, &config, "2022-09-04T20:00:00+03:00");
+    let json = json!({
+        "meta": {
+            "locale": "ru-RU",
+            "timezone": "Europe/Moscow",
+            "client_id": "ru.yandex.searchplugin/7.16 (none none; android 4.4.2)",
+            "interfaces": { "screen": {} }
+        },
+        "session": {
+            "message_id": 1,
+            "session_id": "2eac4854-fce721f3-b845abba-20d60",
+            "skill_id": "3ad36498-f5rd-4079-a14b-788652932056",
+            "application": { "application_id": "47C73714B580ED24" },
+            "new": false
+        },
+        "request": {
+            "command": "позвони ивану петрову на 8999123 и 1к",
+            "original_utterance": "Позвони Ивану Петрову на 8999123 и 1к",
+            "markup": { "dangerous_context": true },
+            "nlu": {
+                "tokens": ["позвони", "ивану", "петрову", "на", "8999123", "и", "1к"],
+                "entities": [
+                    {
+                        "tokens": { "start": 4, "end": 5 },
+                        "type": "YANDEX.NUMBER",
+                        "value": 8_999_123
+                    },
+                    {
+                        "tokens": { "start": 1, "end": 3 },
+                        "type": "YANDEX.FIO",
+                        "value": { "first_name": "иван", "last_name": "петров" }
+                    }
+                ],
+                "intents": {}
+            },
+            "type": "SimpleUtterance"
+        },
+        "state": {},
+        "version": "1.0"
+    });
+    let request: WebhookRequest = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        handle(&request, &context).response.text,
+        "Давайте поговорим о расписании."
+    );
+
+    // Without the markup the message is answered as usual, unless it's profane
+    let polite = test_request(&["когда", "пары"], json!([]), json!({}));
+    assert_ne!(
+        handle(&polite, &context).response.text,
+        "Давайте поговорим о расписании."
+    );
+    let profane = test_request(&["когда", "эти", "ёбаные", "пары"], json!([]), json!({}));
+    assert_eq!(
+        handle(&profane, &context).response.text,
+        "Давайте поговорим о расписании."
+    );
+
+    let logged = std::fs::read_to_string(&log).unwrap();
+    std::fs::remove_file(&log).unwrap();
+    let entries: Vec<serde_json::Value> = logged
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["time"], "2022-09-04T20:00:00+03:00");
+    assert_eq!(entries[0]["reason"], "dangerous_context");
+    assert_eq!(
+        entries[0]["application"],
+        hex::encode(Sha256::digest("47C73714B580ED24"))
+    );
+    assert_eq!(entries[0]["entities"], json!(["имя", "число"]));
+    assert_eq!(entries[1]["reason"], "profanity");
+    // Nothing that was said is logged as it is
+    let command_hash = entries[0]["command_hash"].as_str().unwrap();
+    assert_eq!(command_hash.len(), 64);
+    assert_ne!(
+        command_hash,
+        hex::encode(Sha256::digest("позвони ивану петрову на 8999123 и 1к"))
+    );
+    for word in ["позвони", "ивану", "8999123", "ёбаные"] {
+        assert!(!logged.contains(word), "{word} is in {logged}");
+    }
+}
+
+#[test]
+fn profanity_is_found() {
+    let nlu = |tokens: &[&str]| Nlu {
+        tokens: tokens.iter().map(|token| token.to_string()).collect(),
+        ..Nlu::default()
+    };
+    assert!(is_profane(&nlu(&["бля", "опять", "пары"])));
+    assert!(is_profane(&nlu(&["нахуй", "расписание"])));
+    assert!(is_profane(&nlu(&["ЗАЕБАЛИ"])));
+    // Decent words that begin like the obscene ones
+    assert!(!is_profane(&nlu(&[
+        "блямба",
+        "сукно",
+        "мандарин",
+        "колебался",
+        "требует"
+    ])));
+}
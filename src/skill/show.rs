@@ -0,0 +1,163 @@
+use super::{class_type_name, speech, user_group, Context};
+use crate::{
+    alisa::{Response, ShowItemMeta, WebhookRequest, WebhookResponse},
+    calendar,
+    diff::{diff_weeks, Change},
+    store::Slot,
+};
+use chrono::{DateTime, Datelike, FixedOffset, SecondsFormat, Utc};
+
+const UNKNOWN_GROUP: &str = "Я расскажу о ваших парах, когда вы назовёте мне свою группу.";
+
+/// Dates of the shows are written in UTC with milliseconds, e.g. `2022-09-05T04:30:00.000Z`
+fn show_date(time: DateTime<FixedOffset>) -> String {
+    time.with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Slots that a change touches
+fn changed_slots(change: &Change) -> Vec<Slot> {
+    match change {
+        Change::Added { slot, .. }
+        | Change::Removed { slot, .. }
+        | Change::Restaffed { slot, .. } => vec![*slot],
+        Change::Moved { from, to, .. } => vec![*from, *to],
+    }
+}
+
+/// Tells about the classes of the day in Alice's morning show:
+/// how many there are, when and where the first one is, and whether the schedule changed
+pub fn morning(request: &WebhookRequest, context: &Context) -> WebhookResponse {
+    let today = context.now.date_naive();
+    let Some(user_group) = user_group(request, context.config) else {
+        return WebhookResponse::new(Response::new(UNKNOWN_GROUP));
+    };
+    let Some(week) = context
+        .schedules
+        .find_week(&user_group.group, user_group.subgroup)
+    else {
+        return WebhookResponse::new(Response::new(format!(
+            "Я не нашла расписание группы {}.",
+            user_group.group
+        )));
+    };
+    let semester_start = context.config.semester_start(today);
+    let classes: Vec<_> = calendar::day_classes(week, semester_start, today).collect();
+    let (mut text, mut tts) = match classes.first() {
+        None => (
+            String::from("Сегодня у вас нет пар."),
+            String::from("Сегодня у вас нет пар."),
+        ),
+        Some((slot, class)) => {
+            let count = speech::classes_count(classes.len() as u32);
+            let time = calendar::format_time(calendar::lesson_start(slot.lesson));
+            let class_type = class_type_name(&class.class_type);
+            let text = format!(
+                "Сегодня у вас {count}. Первая — в {time}, {}, {class_type}, аудитория {}.",
+                class.name, class.room
+            );
+            let tts = format!(
+                "Сегодня у вас {count}. {} Первая — в {time}, {}, {}, аудитория {}.",
+                speech::PAUSE,
+                speech::expand(&class.name),
+                speech::expand(&class_type),
+                speech::room(&class.room),
+            );
+            (text, tts)
+        }
+    };
+
+    // A group that wasn't parsed yet a day ago has nothing to compare with
+    let before = context
+        .yesterday
+        .and_then(|yesterday| yesterday.find_week(&user_group.group, user_group.subgroup));
+    let changes = before.map_or_else(Vec::new, |before| diff_weeks(Some(before), Some(week)));
+    if !changes.is_empty() {
+        let day = today.weekday().num_days_from_monday() as u8;
+        let parity = calendar::week_parity(semester_start, today);
+        let today_changed = changes
+            .iter()
+            .flat_map(changed_slots)
+            .any(|slot| slot.day == day && slot.week == parity);
+        let changed = if today_changed {
+            "Со вчерашнего дня расписание на сегодня изменилось."
+        } else {
+            "Со вчерашнего дня расписание изменилось."
+        };
+        text = format!("{text} {changed}");
+        tts = format!("{tts} {} {changed}", speech::PAUSE);
+    }
+
+    let subgroup = user_group
+        .subgroup
+        .map_or_else(String::new, |subgroup| format!("-{subgroup}"));
+    let tomorrow = today.succ_opt().and_then(|date| {
+        date.and_time(calendar::lesson_start(0))
+            .and_local_timezone(calendar::moscow())
+            .single()
+    });
+    let meta = ShowItemMeta {
+        content_id: format!("{today}-{}{subgroup}", user_group.group),
+        title: Some(format!("Пары группы {}", user_group.group)),
+        title_tts: None,
+        publication_date: show_date(context.now),
+        expiration_date: tomorrow.map(show_date),
+    };
+    WebhookResponse::new(Response {
+        tts: Some(tts),
+        show_item_meta: Some(meta),
+        end_session: true,
+        ..Response::new(text)
+    })
+}
+
+#[test]
+fn morning_show_tells_about_the_day() {
+    use super::{handle, test_config, test_context, test_course, test_request, test_schedules};
+    use crate::{alisa::InputType, GroupInfo, Week};
+    use serde_json::json;
+
+    let schedules = test_schedules();
+    let config = test_config();
+    let pull = |state: serde_json::Value, yesterday| {
+        // Monday morning of a lower week
+        let context = Context {
+            yesterday,
+            ..test_context(&schedules, &config, "2022-09-05T07:30:00+03:00")
+        };
+        let mut request = test_request(&[], json!([]), state);
+        request.request.request_type = InputType::ShowPull;
+        handle(&request, &context).response
+    };
+    let state = json!({ "application": { "group": "БИВТ-21-15", "subgroup": 1 } });
+
+    let response = pull(state.clone(), None);
+    assert_eq!(
+        response.text,
+        "Сегодня у вас одна пара. Первая — в 10:50, Математика, лекция, аудитория Б-436."
+    );
+    assert_eq!(
+        response.show_item_meta,
+        Some(ShowItemMeta {
+            content_id: String::from("2022-09-05-БИВТ-21-15-1"),
+            title: Some(String::from("Пары группы БИВТ-21-15")),
+            title_tts: None,
+            publication_date: String::from("2022-09-05T04:30:00.000Z"),
+            expiration_date: Some(String::from("2022-09-06T06:00:00.000Z")),
+        })
+    );
+    assert!(response.end_session);
+
+    // Yesterday the lecture wasn't in the schedule yet
+    let before = test_course(vec![GroupInfo::from_weeks(
+        String::from("БИВТ-21-15"),
+        vec![(Some(1), Week::default()), (Some(2), Week::default())],
+    )]);
+    assert_eq!(
+        pull(state, Some(&before)).text,
+        "Сегодня у вас одна пара. Первая — в 10:50, Математика, лекция, аудитория Б-436. \
+        Со вчерашнего дня расписание на сегодня изменилось."
+    );
+
+    assert_eq!(pull(json!({}), None).text, UNKNOWN_GROUP);
+}
@@ -0,0 +1,235 @@
+use super::{handle, Config, Context};
+use crate::{
+    alisa::{
+        Application, Button, Entity, InputType, Interfaces, Meta, Nlu, Request, Response, Session,
+        State, WebhookRequest,
+    },
+    calendar,
+    store::Schedules,
+};
+use chrono::{DateTime, FixedOffset};
+use serde_json::json;
+
+/// A conversation of one user with the skill, without Dialogs in between.
+/// The states the skill sets are sent back with the next message, the way Dialogs do it
+pub struct Dialog<'a> {
+    schedules: &'a Schedules,
+    config: &'a Config,
+    pub now: DateTime<FixedOffset>,
+    state: State,
+    message_id: u64,
+    /// Buttons of the last answer, they can be pressed in the next message
+    buttons: Vec<Button>,
+}
+
+impl<'a> Dialog<'a> {
+    pub fn new(schedules: &'a Schedules, config: &'a Config, now: DateTime<FixedOffset>) -> Self {
+        Self {
+            schedules,
+            config,
+            now,
+            state: State::default(),
+            message_id: 0,
+            buttons: Vec::new(),
+        }
+    }
+
+    /// Says a message. `entities` replace the ones found in the words, if given
+    pub fn say(&mut self, text: &str, entities: Option<Vec<Entity>>) -> Response {
+        let mut nlu = Nlu::from_text(text);
+        if let Some(entities) = entities {
+            nlu.entities = entities;
+        }
+        self.send(Request {
+            command: nlu.tokens.join(" "),
+            original_utterance: String::from(text),
+            markup: None,
+            nlu,
+            payload: None,
+            request_type: InputType::SimpleUtterance,
+            show_type: None,
+        })
+    }
+
+    /// Presses a button of the last answer, [`None`] if it had no such button
+    pub fn press(&mut self, title: &str) -> Option<Response> {
+        let button = self.buttons.iter().find(|button| button.title == title)?;
+        let request = Request {
+            command: String::new(),
+            original_utterance: String::new(),
+            markup: None,
+            nlu: Nlu::default(),
+            payload: button.payload.clone(),
+            request_type: InputType::ButtonPressed,
+            show_type: None,
+        };
+        Some(self.send(request))
+    }
+
+    fn send(&mut self, request: Request) -> Response {
+        let request = WebhookRequest {
+            meta: Meta {
+                locale: String::from("ru-RU"),
+                timezone: String::from("Europe/Moscow"),
+                client_id: String::from("ru.yandex.searchplugin/7.16 (none none; android 4.4.2)"),
+                interfaces: Interfaces {
+                    screen: Some(json!({})),
+                    ..Interfaces::default()
+                },
+            },
+            request,
+            session: Session {
+                message_id: self.message_id,
+                session_id: String::from("simulated-session"),
+                skill_id: String::from("simulated-skill"),
+                user: None,
+                application: Application {
+                    application_id: String::from("simulated-application"),
+                },
+                new: self.message_id == 0,
+            },
+            state: self.state.clone(),
+            version: String::from("1.0"),
+        };
+        let context = Context::new(self.schedules, self.now, self.config);
+        let response = handle(&request, &context);
+        self.state.apply(&response);
+        self.message_id += 1;
+        self.buttons.clone_from(&response.response.buttons);
+        response.response
+    }
+}
+
+/// What happened when a script was played
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    /// The messages and the answers, the way they are written in the scripts
+    pub lines: Vec<String>,
+    /// Answers that didn't match the expected ones and the lines that couldn't be played
+    pub failures: Vec<String>,
+}
+
+/// Plays a line of a script, returning what is wrong with it
+fn play_line(
+    dialog: &mut Dialog,
+    line: &str,
+    last: &mut Option<Response>,
+    lines: &mut Vec<String>,
+) -> Result<(), String> {
+    if let Some(time) = line.strip_prefix("now ") {
+        dialog.now = DateTime::parse_from_rfc3339(time.trim())
+            .map_err(|error| format!("couldn't parse the time {time}: {error}"))?;
+    } else if let Some(message) = line.strip_prefix('>') {
+        let message = message.trim();
+        lines.push(format!("> {message}"));
+        let response = match message
+            .strip_prefix('[')
+            .and_then(|message| message.strip_suffix(']'))
+        {
+            Some(title) => dialog
+                .press(title)
+                .ok_or_else(|| format!("the last answer has no button {title}"))?,
+            None => match message.split_once(" | ") {
+                Some((text, entities)) => {
+                    let entities = serde_json::from_str(entities)
+                        .map_err(|error| format!("couldn't parse the entities: {error}"))?;
+                    dialog.say(text, Some(entities))
+                }
+                None => dialog.say(message, None),
+            },
+        };
+        lines.push(format!("< {}", response.text));
+        if let Some(tts) = &response.tts {
+            lines.push(format!("~ {tts}"));
+        }
+        *last = Some(response);
+    } else if let Some(expected) = line.strip_prefix('<') {
+        let actual = last.as_ref().map(|response| response.text.as_str());
+        if actual != Some(expected.trim()) {
+            return Err(format!(
+                "expected the answer \"{}\", got {actual:?}",
+                expected.trim()
+            ));
+        }
+    } else if let Some(expected) = line.strip_prefix('~') {
+        let actual = last
+            .as_ref()
+            .map(|response| response.tts.as_deref().unwrap_or(&response.text));
+        if actual != Some(expected.trim()) {
+            return Err(format!(
+                "expected the speech \"{}\", got {actual:?}",
+                expected.trim()
+            ));
+        }
+    } else {
+        return Err(format!("don't know what to do with \"{line}\""));
+    }
+    Ok(())
+}
+
+/// Plays a script of a conversation, line by line:
+/// - `now <RFC 3339>` sets the clock, the current time in Moscow is used until then
+/// - `> <text>` says a message, `> <text> | <entities JSON>` with the entities given
+/// - `> [<title>]` presses a button of the last answer
+/// - `< <text>` expects the text of the last answer, `~ <tts>` expects what was spoken
+/// - empty lines and lines starting with `#` are skipped
+pub fn play(script: &str, schedules: &Schedules, config: &Config) -> Transcript {
+    let mut dialog = Dialog::new(schedules, config, calendar::now());
+    let mut transcript = Transcript::default();
+    let mut last = None;
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Err(error) = play_line(&mut dialog, line, &mut last, &mut transcript.lines) {
+            transcript
+                .failures
+                .push(format!("line {}: {error}", index + 1));
+        }
+    }
+    transcript
+}
+
+#[test]
+fn scripts_are_played() {
+    let schedules = super::test_schedules();
+    let config = super::test_config();
+    let script = "
+        # Sunday evening, the next week is a lower one
+        now 2022-09-04T20:00:00+03:00
+        > Когда завтра первая пара?
+        < Я пока не знаю, в какой вы группе. Назовите её, например, БИВТ-21-15.
+        > бивт 21 15
+        < В какой подгруппе группы БИВТ-21-15 вы учитесь: 1 или 2?
+        > первая
+        < Запомнила: группа БИВТ-21-15, подгруппа 1. Теперь спросите, например, когда завтра первая пара.
+        > когда завтра первая пара
+        < Завтра первая пара в 10:50 — Математика, лекция, аудитория Б-436.
+        ~ Завтра у вас одна пара. sil <[300]> Первой будет вторая пара в 10:50. sil <[300]> Математика, лекция, аудитория бэ четыреста тридцать шесть.
+        > [Следующая пара]
+        < Следующая пара завтра в 10:50 — Математика, лекция, аудитория Б-436.
+    ";
+    let transcript = play(script, &schedules, &config);
+    assert_eq!(transcript.failures, Vec::<String>::new());
+    assert_eq!(transcript.lines[0], "> Когда завтра первая пара?");
+
+    let script = "
+        now 2022-09-04T20:00:00+03:00
+        > привет
+        < Пока
+        > [Завтра]
+        wait
+    ";
+    assert_eq!(
+        play(script, &schedules, &config).failures,
+        [
+            format!(
+                "line 4: expected the answer \"Пока\", got Some(\"{}\")",
+                super::GREETING
+            ),
+            String::from("line 5: the last answer has no button Завтра"),
+            String::from("line 6: don't know what to do with \"wait\""),
+        ]
+    );
+}
@@ -0,0 +1,270 @@
+/// A pause between the parts of an answer
+pub const PAUSE: &str = "sil <[300]>";
+
+/// Abbreviations of subjects and class types found in the schedule and how they are read
+const ABBREVIATIONS: [(&str, &str); 12] = [
+    ("ин.яз.", "иностранный язык"),
+    ("иняз", "иностранный язык"),
+    ("физ-ра", "физическая культура"),
+    ("физра", "физическая культура"),
+    ("бжд", "безопасность жизнедеятельности"),
+    ("ооп", "объектно-ориентированное программирование"),
+    ("твимс", "теория вероятностей и математическая статистика"),
+    ("матан", "математический анализ"),
+    ("лаб.", "лабораторная работа"),
+    ("пр.", "практика"),
+    ("лек.", "лекция"),
+    ("лекционные", "лекция"),
+];
+
+/// Grammatical gender of the counted noun, `одна пара` but `один корпус`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gender {
+    Masculine,
+    Feminine,
+}
+
+const UNITS: [&str; 20] = [
+    "ноль",
+    "один",
+    "два",
+    "три",
+    "четыре",
+    "пять",
+    "шесть",
+    "семь",
+    "восемь",
+    "девять",
+    "десять",
+    "одиннадцать",
+    "двенадцать",
+    "тринадцать",
+    "четырнадцать",
+    "пятнадцать",
+    "шестнадцать",
+    "семнадцать",
+    "восемнадцать",
+    "девятнадцать",
+];
+
+const TENS: [&str; 10] = [
+    "",
+    "",
+    "двадцать",
+    "тридцать",
+    "сорок",
+    "пятьдесят",
+    "шестьдесят",
+    "семьдесят",
+    "восемьдесят",
+    "девяносто",
+];
+
+const HUNDREDS: [&str; 10] = [
+    "",
+    "сто",
+    "двести",
+    "триста",
+    "четыреста",
+    "пятьсот",
+    "шестьсот",
+    "семьсот",
+    "восемьсот",
+    "девятьсот",
+];
+
+const ORDINALS: [&str; 8] = [
+    "первая",
+    "вторая",
+    "третья",
+    "четвёртая",
+    "пятая",
+    "шестая",
+    "седьмая",
+    "восьмая",
+];
+
+/// How the letters are called when spelled out, e.g. `бэ` in `Б-436` or in "в корпусе бэ"
+const LETTER_NAMES: [(char, &str); 17] = [
+    ('А', "а"),
+    ('Б', "бэ"),
+    ('В', "вэ"),
+    ('Г', "гэ"),
+    ('Д', "дэ"),
+    ('Е', "е"),
+    ('К', "ка"),
+    ('Л', "эль"),
+    ('М', "эм"),
+    ('Н', "эн"),
+    ('П', "пэ"),
+    ('Р', "эр"),
+    ('С', "эс"),
+    ('Т', "тэ"),
+    ('Ф', "эф"),
+    ('Х', "ха"),
+    ('Ц', "цэ"),
+];
+
+/// How an uppercase letter is called, e.g. `бэ` for `Б`
+pub fn letter_name(letter: char) -> Option<&'static str> {
+    LETTER_NAMES
+        .iter()
+        .find(|(known, _)| *known == letter)
+        .map(|(_, name)| *name)
+}
+
+/// The uppercase letter called by a word, e.g. `Б` for `бэ`
+pub fn named_letter(word: &str) -> Option<char> {
+    LETTER_NAMES
+        .iter()
+        .find(|(_, name)| *name == word)
+        .map(|(letter, _)| *letter)
+}
+
+/// A number below ten thousand with words, e.g. `четыреста тридцать шесть`
+pub fn cardinal(number: u32, gender: Gender) -> String {
+    if number == 0 {
+        return String::from(UNITS[0]);
+    }
+    let mut words = Vec::new();
+    let thousands = number / 1000 % 10;
+    if thousands > 0 {
+        let form = plural(thousands, ["тысяча", "тысячи", "тысяч"]);
+        if thousands > 1 {
+            words.push(cardinal(thousands, Gender::Feminine));
+        }
+        words.push(String::from(form));
+    }
+    let rest = number % 1000;
+    words.push(String::from(HUNDREDS[(rest / 100) as usize]));
+    let rest = rest % 100;
+    if rest >= 20 {
+        words.push(String::from(TENS[(rest / 10) as usize]));
+    }
+    let units = if rest >= 20 { rest % 10 } else { rest };
+    if units > 0 {
+        let word = match (units, gender) {
+            (1, Gender::Feminine) => "одна",
+            (2, Gender::Feminine) => "две",
+            _ => UNITS[units as usize],
+        };
+        words.push(String::from(word));
+    }
+    words.retain(|word| !word.is_empty());
+    words.join(" ")
+}
+
+/// The form of a noun that agrees with the number: `[одна, две, пять]`
+pub fn plural(number: u32, forms: [&str; 3]) -> &str {
+    let [one, few, many] = forms;
+    match (number % 10, number % 100) {
+        (_, 11..=14) => many,
+        (1, _) => one,
+        (2..=4, _) => few,
+        _ => many,
+    }
+}
+
+/// How many classes there are, e.g. `две пары`, `пять пар`
+pub fn classes_count(count: u32) -> String {
+    format!(
+        "{} {}",
+        cardinal(count, Gender::Feminine),
+        plural(count, ["пара", "пары", "пар"])
+    )
+}
+
+/// The number of a class in the day, e.g. `третья пара`. Lessons are counted from zero
+pub fn ordinal_class(lesson: u8) -> String {
+    match ORDINALS.get(lesson as usize) {
+        Some(ordinal) => format!("{ordinal} пара"),
+        None => format!(
+            "пара номер {}",
+            cardinal(lesson as u32 + 1, Gender::Feminine)
+        ),
+    }
+}
+
+/// A room with its letters called and its numbers said with words,
+/// e.g. `бэ четыреста тридцать шесть` for `Б-436`
+pub fn room(room: &str) -> String {
+    let mut words = Vec::new();
+    let mut chars = room.chars().peekable();
+    while let Some(&char) = chars.peek() {
+        if char.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+            // Leading zeros and long numbers are read digit by digit
+            match number.parse() {
+                Ok(value) if !number.starts_with('0') && value < 10_000 => {
+                    words.push(cardinal(value, Gender::Masculine))
+                }
+                _ => words.extend(number.chars().map(|digit| {
+                    let digit = digit.to_digit(10).unwrap_or_default();
+                    String::from(UNITS[digit as usize])
+                })),
+            }
+        } else if char.is_alphabetic() {
+            let mut letters = String::new();
+            while let Some(letter) = chars.next_if(|char| char.is_alphabetic()) {
+                letters.push(letter);
+            }
+            let mut upper = letters.chars().flat_map(char::to_uppercase);
+            let single = upper.next().filter(|_| upper.next().is_none());
+            let name = single.and_then(letter_name);
+            words.push(name.map_or(letters.to_lowercase(), String::from));
+        } else {
+            chars.next();
+        }
+    }
+    words.join(" ")
+}
+
+/// Subject or class type name with its abbreviations expanded
+pub fn expand(name: &str) -> String {
+    name.split(' ')
+        .map(|word| {
+            let lower = word.to_lowercase();
+            ABBREVIATIONS
+                .iter()
+                .find(|(abbreviation, _)| *abbreviation == lower)
+                .map_or_else(|| String::from(word), |(_, full)| String::from(*full))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn numbers_agree() {
+    assert_eq!(classes_count(1), "одна пара");
+    assert_eq!(classes_count(2), "две пары");
+    assert_eq!(classes_count(5), "пять пар");
+    assert_eq!(classes_count(12), "двенадцать пар");
+    assert_eq!(classes_count(22), "двадцать две пары");
+    assert_eq!(
+        cardinal(2021, Gender::Masculine),
+        "две тысячи двадцать один"
+    );
+    assert_eq!(cardinal(1000, Gender::Masculine), "тысяча");
+    assert_eq!(ordinal_class(2), "третья пара");
+    assert_eq!(ordinal_class(9), "пара номер десять");
+}
+
+#[test]
+fn names_are_spoken() {
+    assert_eq!(room("Б-436"), "бэ четыреста тридцать шесть");
+    assert_eq!(room("Л-550а"), "эль пятьсот пятьдесят а");
+    assert_eq!(room("К-012"), "ка ноль один два");
+    assert_eq!(room("Т-101"), "тэ сто один");
+    assert_eq!(named_letter("тэ").and_then(letter_name), Some("тэ"));
+    assert_eq!(room("Спортзал"), "спортзал");
+    assert_eq!(expand("Физ-ра"), "физическая культура");
+    assert_eq!(
+        expand("ТВиМС"),
+        "теория вероятностей и математическая статистика"
+    );
+    assert_eq!(expand("Лекционные"), "лекция");
+    assert_eq!(expand("Математика"), "Математика");
+}
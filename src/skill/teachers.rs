@@ -0,0 +1,427 @@
+use super::{
+    class_type_name, day_name,
+    dialog::{self, Awaiting},
+    speech, Context,
+};
+use crate::{
+    alisa::{Fio, Intent, Response, WebhookRequest, WebhookResponse, YandexEnteties},
+    calendar,
+    store::Occurrence,
+};
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeSet;
+
+/// How far ahead the next class of a teacher is looked for, two weeks cover both parities
+const DAYS_AHEAD: usize = 14;
+
+/// A teacher as the user named them: a surname and maybe the first letters
+/// of the name and the patronymic
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct SpokenName {
+    /// In lower case
+    surname: String,
+    /// In upper case
+    initials: Vec<char>,
+}
+
+fn normalise(word: &str) -> String {
+    word.to_lowercase().replace('ё', "е")
+}
+
+fn initial(word: &str) -> Option<char> {
+    word.chars().next()?.to_uppercase().next()
+}
+
+/// `Иванов И.И.` or `Иванов Иван Иванович` split into the surname and the other words
+fn name_words(name: &str) -> impl Iterator<Item = &str> {
+    name.split([' ', '.']).filter(|word| !word.is_empty())
+}
+
+impl SpokenName {
+    fn from_fio(fio: &Fio) -> Option<Self> {
+        let initials = [&fio.first_name, &fio.patronymic_name]
+            .into_iter()
+            .map_while(|word| initial(word.as_deref()?))
+            .collect();
+        Some(Self {
+            surname: normalise(fio.last_name.as_deref()?),
+            initials,
+        })
+    }
+
+    fn from_words(words: &str) -> Option<Self> {
+        let mut words = name_words(words);
+        Some(Self {
+            surname: normalise(words.next()?),
+            initials: words.filter_map(initial).collect(),
+        })
+    }
+
+    /// The surname is the same and the initials that were said agree
+    fn matches(&self, teacher: &str) -> bool {
+        let mut words = name_words(teacher);
+        let Some(surname) = words.next() else {
+            return false;
+        };
+        normalise(surname) == self.surname
+            && words
+                .filter_map(initial)
+                .zip(&self.initials)
+                .all(|(initial, said)| initial == *said)
+    }
+
+    /// `Иванов`, as the surname is written in the schedule
+    fn surname(&self) -> String {
+        let mut chars = self.surname.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// The teacher named in the `teacher` slot, either with a `YANDEX.FIO` or with any words
+fn spoken_name(intent: &Intent) -> Option<SpokenName> {
+    let slot = intent.slot("teacher")?;
+    match slot.entity() {
+        Some(YandexEnteties::Fio(fio)) => SpokenName::from_fio(fio),
+        _ => SpokenName::from_words(slot.string()?),
+    }
+}
+
+/// Classes of every teacher that is called like that, by their full names
+fn namesakes(context: &Context, name: &SpokenName) -> Vec<(String, Vec<Occurrence>)> {
+    let classes = context.schedules.find_classes(|class| {
+        class
+            .teacher
+            .as_deref()
+            .is_some_and(|teacher| name.matches(teacher))
+    });
+    let teachers: BTreeSet<&str> = classes
+        .iter()
+        .filter_map(|occurrence| occurrence.class.teacher.as_deref())
+        .collect();
+    teachers
+        .into_iter()
+        .map(|teacher| {
+            let classes = classes
+                .iter()
+                .filter(|occurrence| occurrence.class.teacher.as_deref() == Some(teacher))
+                .cloned()
+                .collect();
+            (String::from(teacher), classes)
+        })
+        .collect()
+}
+
+/// The class the teacher is giving now or will give next, with all the groups that attend it
+fn next_class<'a>(
+    context: &Context,
+    classes: &'a [Occurrence],
+) -> Option<(NaiveDate, Vec<&'a Occurrence>)> {
+    let today = context.now.date_naive();
+    let now = context.now.time();
+    today.iter_days().take(DAYS_AHEAD).find_map(|date| {
+        let parity = calendar::week_parity(context.config.semester_start(date), date);
+        let day = date.weekday().num_days_from_monday() as u8;
+        let lesson = classes
+            .iter()
+            .filter(|occurrence| occurrence.slot.day == day && occurrence.slot.week == parity)
+            .map(|occurrence| occurrence.slot.lesson)
+            .filter(|lesson| date > today || calendar::lesson_end(*lesson) > now)
+            .min()?;
+        let attended = classes
+            .iter()
+            .filter(|occurrence| {
+                occurrence.slot.day == day
+                    && occurrence.slot.week == parity
+                    && occurrence.slot.lesson == lesson
+            })
+            .collect();
+        Some((date, attended))
+    })
+}
+
+/// Tells where and when the teacher teaches now or next
+fn where_is(context: &Context, teacher: &str, classes: &[Occurrence]) -> WebhookResponse {
+    let Some((date, attended)) = next_class(context, classes) else {
+        return WebhookResponse::new(Response::new(format!(
+            "{teacher} в ближайшие две недели не ведёт пар."
+        )));
+    };
+    let today = context.now.date_naive();
+    let first = attended[0];
+    let lesson = first.slot.lesson;
+    let started = date == today && calendar::lesson_start(lesson) <= context.now.time();
+    let when = if started {
+        format!(
+            "сейчас, до {}",
+            calendar::format_time(calendar::lesson_end(lesson))
+        )
+    } else {
+        format!(
+            "{} в {}",
+            day_name(date, today).to_lowercase(),
+            calendar::format_time(calendar::lesson_start(lesson))
+        )
+    };
+    let groups: BTreeSet<&str> = attended
+        .iter()
+        .map(|occurrence| occurrence.group.as_str())
+        .collect();
+    let groups = groups.into_iter().collect::<Vec<_>>().join(", ");
+    let class = &first.class;
+    let class_type = class_type_name(&class.class_type);
+    let text = format!(
+        "{teacher} {when}: {}, {class_type}, аудитория {}. Группы: {groups}.",
+        class.name, class.room
+    );
+    let tts = format!(
+        "{teacher} {when}: {}, {}, аудитория {}. {} Группы: {groups}.",
+        speech::expand(&class.name),
+        speech::expand(&class_type),
+        speech::room(&class.room),
+        speech::PAUSE,
+    );
+    WebhookResponse::new(Response {
+        tts: Some(tts),
+        ..Response::new(text)
+    })
+}
+
+/// Answers with the only teacher or asks which one of the namesakes the user meant
+fn answer(
+    context: &Context,
+    surname: &str,
+    namesakes: &[(String, Vec<Occurrence>)],
+) -> WebhookResponse {
+    match namesakes {
+        [] => WebhookResponse::new(Response::new(format!(
+            "Я не нашла преподавателя {surname} в расписании."
+        ))),
+        [(teacher, classes)] => where_is(context, teacher, classes),
+        _ => {
+            let candidates: Vec<String> = namesakes.iter().map(|(name, _)| name.clone()).collect();
+            let (last, rest) = candidates.split_last().unwrap();
+            let question = Response::new(format!(
+                "Я знаю нескольких преподавателей с фамилией {surname}. \
+                Кого вы имели в виду: {} или {last}?",
+                rest.join(", ")
+            ));
+            dialog::ask(question, Awaiting::Teacher { candidates }, context.now)
+        }
+    }
+}
+
+/// `teacher.where`: "где сейчас Иванов", needs the `teacher` slot
+pub fn teacher_where(
+    intent: &Intent,
+    _: &WebhookRequest,
+    context: &Context,
+) -> Option<WebhookResponse> {
+    let name = spoken_name(intent)?;
+    let namesakes = namesakes(context, &name);
+    Some(answer(context, &name.surname(), &namesakes))
+}
+
+/// The `token` starts with the name or the patronymic of the `teacher`, or with their initial
+fn mentions(teacher: &str, token: &str) -> bool {
+    let token = normalise(token);
+    name_words(teacher)
+        .skip(1)
+        .any(|word| token.starts_with(&normalise(word)))
+}
+
+/// Picks one of the namesakes by the name and the patronymic the user said,
+/// [`None`] if the message doesn't tell them apart
+pub fn clarify(
+    request: &WebhookRequest,
+    context: &Context,
+    candidates: &[String],
+) -> Option<WebhookResponse> {
+    let nlu = &request.request.nlu;
+    let surname = candidates
+        .first()
+        .and_then(|name| name_words(name).next())?;
+    let from_fio = nlu
+        .entities
+        .iter()
+        .find_map(|entity| match &entity.named_entity {
+            YandexEnteties::Fio(fio) => Some(fio),
+            _ => None,
+        })
+        .map(|fio| SpokenName {
+            surname: normalise(surname),
+            initials: [&fio.first_name, &fio.patronymic_name]
+                .into_iter()
+                .map_while(|word| initial(word.as_deref()?))
+                .collect(),
+        });
+    let teacher = match from_fio {
+        Some(name) if !name.initials.is_empty() => {
+            let chosen: Vec<&String> = candidates
+                .iter()
+                .filter(|candidate| name.matches(candidate))
+                .collect();
+            let [teacher] = chosen[..] else {
+                return None;
+            };
+            teacher
+        }
+        // Without a name that Dialogs found, the words that start like the names are counted,
+        // so that "тот, который Андрей" is understood too
+        _ => {
+            let said = |candidate: &str| {
+                nlu.tokens
+                    .iter()
+                    .filter(|token| normalise(token) != normalise(surname))
+                    .filter(|token| mentions(candidate, token))
+                    .count()
+            };
+            let most = candidates
+                .iter()
+                .map(|candidate| said(candidate))
+                .max()
+                .filter(|most| *most > 0)?;
+            let chosen: Vec<&String> = candidates
+                .iter()
+                .filter(|candidate| said(candidate) == most)
+                .collect();
+            let [teacher] = chosen[..] else {
+                return None;
+            };
+            teacher
+        }
+    };
+    let name = SpokenName {
+        surname: normalise(surname),
+        initials: Vec::new(),
+    };
+    let namesakes = namesakes(context, &name);
+    let classes = namesakes
+        .iter()
+        .find(|(name, _)| name == teacher)
+        .map(|(_, classes)| classes.as_slice())
+        .unwrap_or_default();
+    Some(where_is(context, teacher, classes))
+}
+
+#[test]
+fn teachers_are_found() {
+    use super::{handle, test_config, test_context, test_course, test_request};
+    use crate::{
+        store::{set_class, Slot, WeekParity},
+        Class, ClassType, GroupInfo, Week,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    let class = |name: &str, teacher: &str, room: &str| Class {
+        name: Arc::from(name),
+        class_type: ClassType::Lection,
+        teacher: Some(Arc::from(teacher)),
+        room: Arc::from(room),
+    };
+    let slot = |day, lesson, week| Slot { day, lesson, week };
+    // Both groups attend the stream lecture of Петров on Monday of a lower week
+    let mut first = Week::default();
+    let mut second = Week::default();
+    let lecture = class("Физика", "Петров Пётр Петрович", "Б-436");
+    set_class(&mut first, slot(0, 2, WeekParity::Lower), lecture.clone());
+    set_class(&mut second, slot(0, 2, WeekParity::Lower), lecture);
+    set_class(
+        &mut first,
+        slot(1, 0, WeekParity::Lower),
+        class("Математика", "Иванов А.Б.", "Л-550"),
+    );
+    set_class(
+        &mut second,
+        slot(2, 3, WeekParity::Upper),
+        class("Химия", "Иванов В.Г.", "К-212"),
+    );
+    set_class(
+        &mut second,
+        slot(3, 1, WeekParity::Lower),
+        class("История", "Семёнов С.С.", "А-305"),
+    );
+    let schedules = test_course(vec![
+        GroupInfo::from_weeks(String::from("БИВТ-21-15"), vec![(None, first)]),
+        GroupInfo::from_weeks(String::from("БИВТ-21-16"), vec![(None, second)]),
+    ]);
+    let config = test_config();
+    // Monday of a lower week, during the lecture
+    let context = test_context(&schedules, &config, "2022-09-05T13:00:00+03:00");
+    let fio = |start: u64, end: u64, fio: serde_json::Value| json!({ "tokens": { "start": start, "end": end }, "type": "YANDEX.FIO", "value": fio });
+    let ask = |tokens: &[&str], entities: serde_json::Value, session: serde_json::Value| {
+        let state = json!({ "session": session });
+        handle(&test_request(tokens, entities, state), &context)
+    };
+
+    let petrov = fio(1, 2, json!({ "last_name": "петров" }));
+    assert_eq!(
+        ask(&["где", "петров"], json!([petrov]), json!({}))
+            .response
+            .text,
+        "Петров Пётр Петрович сейчас, до 14:15: Физика, лекция, аудитория Б-436. \
+        Группы: БИВТ-21-15, БИВТ-21-16."
+    );
+    let sidorov = fio(1, 2, json!({ "last_name": "сидоров" }));
+    assert_eq!(
+        ask(&["где", "сидоров"], json!([sidorov]), json!({}))
+            .response
+            .text,
+        "Я не нашла преподавателя Сидоров в расписании."
+    );
+
+    // "Ё" is found whether it was said or not
+    let semenov = fio(1, 2, json!({ "last_name": "семенов" }));
+    assert_eq!(
+        ask(&["где", "семенов"], json!([semenov]), json!({}))
+            .response
+            .text,
+        "Семёнов С.С. в четверг в 10:50: История, лекция, аудитория А-305. \
+        Группы: БИВТ-21-16."
+    );
+
+    // The initials pick one of the namesakes
+    let ivanov_v = fio(
+        1,
+        3,
+        json!({ "first_name": "виктор", "last_name": "иванов" }),
+    );
+    assert_eq!(
+        ask(&["где", "виктор", "иванов"], json!([ivanov_v]), json!({}))
+            .response
+            .text,
+        "Иванов В.Г. в среду в 14:30: Химия, лекция, аудитория К-212. \
+        Группы: БИВТ-21-16."
+    );
+
+    // Otherwise the skill asks which one and takes the answer from the next message
+    let ivanov = fio(1, 2, json!({ "last_name": "иванов" }));
+    let response = ask(&["где", "иванов"], json!([ivanov]), json!({}));
+    assert_eq!(
+        response.response.text,
+        "Я знаю нескольких преподавателей с фамилией Иванов. \
+        Кого вы имели в виду: Иванов А.Б. или Иванов В.Г.?"
+    );
+    let session = response.session_state.unwrap();
+    assert_eq!(
+        ask(&["андрей", "борисович"], json!([]), session.clone())
+            .response
+            .text,
+        "Иванов А.Б. завтра в 9:00: Математика, лекция, аудитория Л-550. \
+        Группы: БИВТ-21-15."
+    );
+    assert_eq!(
+        ask(&["тот", "который", "виктор"], json!([]), session.clone())
+            .response
+            .text,
+        "Иванов В.Г. в среду в 14:30: Химия, лекция, аудитория К-212. \
+        Группы: БИВТ-21-16."
+    );
+    assert_eq!(
+        ask(&["привет"], json!([]), session).response.text,
+        super::NOT_UNDERSTOOD
+    );
+}
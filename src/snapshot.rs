@@ -0,0 +1,173 @@
+use crate::{interner::NormalisedSchedules, store::Schedules};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Marks the start of every snapshot file
+const MAGIC: &[u8; 8] = b"MISISASN";
+/// Has to be bumped every time the layout of the schedule types changes,
+/// so the snapshots written by older versions are ignored
+pub const VERSION: u32 = 3;
+
+/// Path of the snapshot, which lives alongside the parsed json files
+pub fn path(parsed_dir: &Path) -> PathBuf {
+    parsed_dir.join("schedules.snapshot")
+}
+
+/// Changes whenever a json file in the directory is added, removed or rewritten,
+/// so a snapshot taken of other files isn't read
+pub fn fingerprint(parsed_dir: &Path) -> io::Result<u64> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(parsed_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        files.push((entry.file_name(), metadata.len(), modified.as_nanos()));
+    }
+    files.sort();
+    let mut hasher = Sha256::new();
+    for (name, len, modified) in files {
+        hasher.update(name.as_encoded_bytes());
+        hasher.update([0]);
+        hasher.update(len.to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Writes all the schedules into a single compact binary file,
+/// with the [`fingerprint`] of the json files they were loaded from.
+/// Subjects, teachers and rooms are stored once, in shared symbol tables
+pub fn write(path: &Path, schedules: &Schedules, fingerprint: u64) -> io::Result<()> {
+    // Write into a temporary file first, so a half-written snapshot is never read
+    let temporary_path = path.with_extension("snapshot.tmp");
+    {
+        let mut file = BufWriter::new(File::create(&temporary_path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&fingerprint.to_le_bytes())?;
+        let normalised = NormalisedSchedules::new(schedules);
+        bincode::serialize_into(&mut file, &normalised).map_err(io::Error::other)?;
+        file.flush()?;
+    }
+    std::fs::rename(temporary_path, path)
+}
+
+/// Reads a snapshot
+/// Returns [`None`] if there is no snapshot, it was written by an incompatible version
+/// or of json files with another [`fingerprint`]
+pub fn read(path: &Path, fingerprint: u64) -> io::Result<Option<Schedules>> {
+    let mut file = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut header = [0; MAGIC.len() + 4 + 8];
+    file.read_exact(&mut header)?;
+    let (magic, rest) = header.split_at(MAGIC.len());
+    let (version, written_of) = rest.split_at(4);
+    if magic != MAGIC || version != VERSION.to_le_bytes() || written_of != fingerprint.to_le_bytes()
+    {
+        return Ok(None);
+    }
+    let normalised: NormalisedSchedules =
+        bincode::deserialize_from(file).map_err(io::Error::other)?;
+    match normalised.to_schedules() {
+        Some(schedules) => Ok(Some(schedules)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Snapshot references unknown symbols",
+        )),
+    }
+}
+
+#[cfg(test)]
+fn test_schedules(institutes: usize) -> Schedules {
+    use calamine::{open_workbook, Xlsx};
+    let mut excel: Xlsx<_> = open_workbook("test/Test.xlsx").unwrap();
+    let parsed = crate::ExcelData::new(&mut excel).parse();
+    let mut schedules = Schedules::default();
+    for institute in 0..institutes {
+        schedules.insert(format!("test_{institute}"), parsed.to_vec());
+    }
+    schedules
+}
+
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("misisa-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let dir = test_dir("snapshot");
+    let schedules = test_schedules(2);
+    let path = path(&dir);
+    assert_eq!(read(&path, 1).unwrap(), None);
+
+    write(&path, &schedules, 1).unwrap();
+    assert_eq!(read(&path, 1).unwrap(), Some(schedules));
+    // Snapshots of other json files are ignored
+    assert_eq!(read(&path, 2).unwrap(), None);
+
+    // Snapshots of other versions are ignored
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    assert_eq!(read(&path, 1).unwrap(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn broken_snapshots_fall_back_to_json() {
+    use crate::store::Backend;
+
+    let dir = test_dir("broken-snapshot");
+    let schedules = test_schedules(2);
+    for (name, courses) in schedules.institutes() {
+        let file = File::create(dir.join(name).with_extension("json")).unwrap();
+        serde_json::to_writer(file, courses).unwrap();
+    }
+    let path = path(&dir);
+    write(&path, &schedules, fingerprint(&dir).unwrap()).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    assert!(read(&path, fingerprint(&dir).unwrap()).is_err());
+
+    // The json files are loaded and the snapshot is written again
+    assert_eq!(Backend::Json.load(&dir).unwrap(), schedules);
+    assert_eq!(
+        read(&path, fingerprint(&dir).unwrap()).unwrap().as_ref(),
+        Some(&schedules)
+    );
+
+    // A json file changed by hand makes the snapshot stale
+    let mut edited = Schedules::default();
+    edited.insert(String::from("test_0"), Vec::new());
+    edited.insert(
+        String::from("test_1"),
+        schedules.institute("test_1").unwrap().to_vec(),
+    );
+    std::fs::write(dir.join("test_0.json"), "[]").unwrap();
+    assert_eq!(read(&path, fingerprint(&dir).unwrap()).unwrap(), None);
+    assert_eq!(Backend::Json.load(&dir).unwrap(), edited);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,477 @@
+use crate::{
+    store::{set_class, week_classes, Occurrence, Schedules, Slot, WeekParity},
+    Class, ClassType, Course, GroupInfo, Week,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS institutes (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS courses (
+    id INTEGER PRIMARY KEY,
+    institute_id INTEGER NOT NULL REFERENCES institutes(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS groups (
+    id INTEGER PRIMARY KEY,
+    course_id INTEGER NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL
+);
+
+-- A group without subgroups has a single row with a NULL number
+CREATE TABLE IF NOT EXISTS subgroups (
+    id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    number INTEGER
+);
+
+-- week is 0 for the upper week and 1 for the lower one
+CREATE TABLE IF NOT EXISTS slots (
+    id INTEGER PRIMARY KEY,
+    day INTEGER NOT NULL,
+    lesson INTEGER NOT NULL,
+    week INTEGER NOT NULL,
+    UNIQUE (day, lesson, week)
+);
+
+CREATE TABLE IF NOT EXISTS teachers (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS rooms (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+-- class_type is one of Lection, Practice, Lab or Unknown,
+-- class_type_name holds the original name of an Unknown type
+CREATE TABLE IF NOT EXISTS classes (
+    id INTEGER PRIMARY KEY,
+    subgroup_id INTEGER NOT NULL REFERENCES subgroups(id) ON DELETE CASCADE,
+    slot_id INTEGER NOT NULL REFERENCES slots(id),
+    name TEXT NOT NULL,
+    class_type TEXT NOT NULL,
+    class_type_name TEXT,
+    teacher_id INTEGER REFERENCES teachers(id),
+    room_id INTEGER NOT NULL REFERENCES rooms(id)
+);
+
+CREATE INDEX IF NOT EXISTS classes_subgroup ON classes(subgroup_id);
+CREATE INDEX IF NOT EXISTS classes_slot ON classes(slot_id);
+CREATE INDEX IF NOT EXISTS classes_teacher ON classes(teacher_id);
+CREATE INDEX IF NOT EXISTS classes_room ON classes(room_id);
+";
+
+/// Columns of an [`Occurrence`], filtered by a `WHERE` clause appended to it
+const OCCURRENCES: &str = "
+SELECT institutes.name, courses.name, groups.name, subgroups.number,
+    slots.day, slots.lesson, slots.week,
+    classes.name, classes.class_type, classes.class_type_name, teachers.name, rooms.name
+FROM classes
+JOIN subgroups ON subgroups.id = classes.subgroup_id
+JOIN groups ON groups.id = subgroups.group_id
+JOIN courses ON courses.id = groups.course_id
+JOIN institutes ON institutes.id = courses.institute_id
+JOIN slots ON slots.id = classes.slot_id
+JOIN rooms ON rooms.id = classes.room_id
+LEFT JOIN teachers ON teachers.id = classes.teacher_id
+";
+
+const OCCURRENCES_ORDER: &str = "
+ORDER BY slots.day, slots.lesson, slots.week,
+    institutes.name, courses.position, groups.position, subgroups.position
+";
+
+const fn week_to_sql(week: WeekParity) -> u8 {
+    match week {
+        WeekParity::Upper => 0,
+        WeekParity::Lower => 1,
+    }
+}
+
+const fn week_from_sql(week: u8) -> WeekParity {
+    if week == 0 {
+        WeekParity::Upper
+    } else {
+        WeekParity::Lower
+    }
+}
+
+fn class_type_to_sql(class_type: &ClassType) -> (&'static str, Option<&str>) {
+    match class_type {
+        ClassType::Lection => ("Lection", None),
+        ClassType::Practice => ("Practice", None),
+        ClassType::Lab => ("Lab", None),
+        ClassType::Unknown(name) => ("Unknown", Some(name)),
+    }
+}
+
+fn class_type_from_sql(class_type: &str, name: Option<String>) -> ClassType {
+    match class_type {
+        "Lection" => ClassType::Lection,
+        "Practice" => ClassType::Practice,
+        "Lab" => ClassType::Lab,
+        _ => ClassType::Unknown(name.unwrap_or_default()),
+    }
+}
+
+/// Reads a class from 5 columns starting at `start`:
+/// name, class_type, class_type_name, teacher and room
+fn class_from_row(row: &Row, start: usize) -> rusqlite::Result<Class> {
+    let class_type: String = row.get(start + 1)?;
+    Ok(Class {
+        name: row.get(start)?,
+        class_type: class_type_from_sql(&class_type, row.get(start + 2)?),
+        teacher: row.get(start + 3)?,
+        room: row.get(start + 4)?,
+    })
+}
+
+fn occurrence_from_row(row: &Row) -> rusqlite::Result<Occurrence> {
+    Ok(Occurrence {
+        institute: row.get(0)?,
+        course: row.get(1)?,
+        group: row.get(2)?,
+        subgroup: row.get(3)?,
+        slot: Slot {
+            day: row.get(4)?,
+            lesson: row.get(5)?,
+            week: week_from_sql(row.get(6)?),
+        },
+        class: class_from_row(row, 7)?,
+    })
+}
+
+/// Deletes the teachers and rooms no class references anymore
+fn delete_unused_names(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute_batch(
+        "DELETE FROM teachers WHERE id NOT IN
+            (SELECT teacher_id FROM classes WHERE teacher_id IS NOT NULL);
+        DELETE FROM rooms WHERE id NOT IN (SELECT room_id FROM classes);",
+    )
+}
+
+/// Normalised storage of the parsed schedules
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        {
+            let mut insert_slot = connection
+                .prepare("INSERT OR IGNORE INTO slots (day, lesson, week) VALUES (?1, ?2, ?3)")?;
+            for day in 0..7u8 {
+                for lesson in 0..7u8 {
+                    for week in [WeekParity::Upper, WeekParity::Lower] {
+                        insert_slot.execute(params![day, lesson, week_to_sql(week)])?;
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// The connection, even if a query panicked while holding it:
+    /// its transaction was rolled back when the panic dropped it
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces all the courses of an institute with the new ones
+    pub fn insert_institute(&self, institute: &str, courses: &[Course]) -> rusqlite::Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM institutes WHERE name = ?1", [institute])?;
+        transaction.execute("INSERT INTO institutes (name) VALUES (?1)", [institute])?;
+        let institute_id = transaction.last_insert_rowid();
+        {
+            let mut insert_course = transaction.prepare(
+                "INSERT INTO courses (institute_id, position, name) VALUES (?1, ?2, ?3)",
+            )?;
+            let mut insert_group = transaction
+                .prepare("INSERT INTO groups (course_id, position, name) VALUES (?1, ?2, ?3)")?;
+            let mut insert_subgroup = transaction.prepare(
+                "INSERT INTO subgroups (group_id, position, number) VALUES (?1, ?2, ?3)",
+            )?;
+            let mut insert_teacher =
+                transaction.prepare("INSERT OR IGNORE INTO teachers (name) VALUES (?1)")?;
+            let mut select_teacher =
+                transaction.prepare("SELECT id FROM teachers WHERE name = ?1")?;
+            let mut insert_room =
+                transaction.prepare("INSERT OR IGNORE INTO rooms (name) VALUES (?1)")?;
+            let mut select_room = transaction.prepare("SELECT id FROM rooms WHERE name = ?1")?;
+            let mut select_slot = transaction
+                .prepare("SELECT id FROM slots WHERE day = ?1 AND lesson = ?2 AND week = ?3")?;
+            let mut insert_class = transaction.prepare(
+                "INSERT INTO classes
+                    (subgroup_id, slot_id, name, class_type, class_type_name, teacher_id, room_id)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+
+            for (course_position, course) in courses.iter().enumerate() {
+                let course_id =
+                    insert_course.insert(params![institute_id, course_position, course.name])?;
+                for (group_position, group) in course.groups.iter().enumerate() {
+                    let group_id =
+                        insert_group.insert(params![course_id, group_position, group.name])?;
+                    for (subgroup_position, (number, week)) in group.weeks().into_iter().enumerate()
+                    {
+                        let subgroup_id =
+                            insert_subgroup.insert(params![group_id, subgroup_position, number])?;
+                        for (slot, class) in week_classes(week) {
+                            let slot_id: i64 = select_slot.query_row(
+                                params![slot.day, slot.lesson, week_to_sql(slot.week)],
+                                |row| row.get(0),
+                            )?;
+                            let teacher_id: Option<i64> = match &class.teacher {
+                                Some(teacher) => {
+                                    insert_teacher.execute([teacher])?;
+                                    Some(select_teacher.query_row([teacher], |row| row.get(0))?)
+                                }
+                                None => None,
+                            };
+                            insert_room.execute([&class.room])?;
+                            let room_id: i64 =
+                                select_room.query_row([&class.room], |row| row.get(0))?;
+                            let (class_type, class_type_name) =
+                                class_type_to_sql(&class.class_type);
+                            insert_class.execute(params![
+                                subgroup_id,
+                                slot_id,
+                                class.name,
+                                class_type,
+                                class_type_name,
+                                teacher_id,
+                                room_id
+                            ])?;
+                        }
+                    }
+                }
+            }
+        }
+        delete_unused_names(&transaction)?;
+        transaction.commit()
+    }
+
+    /// Deletes an institute with all its courses, doesn't fail if there is no such institute
+    pub fn remove_institute(&self, institute: &str) -> rusqlite::Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM institutes WHERE name = ?1", [institute])?;
+        delete_unused_names(&transaction)?;
+        transaction.commit()
+    }
+
+    pub fn has_institute(&self, institute: &str) -> rusqlite::Result<bool> {
+        let connection = self.connection();
+        let id: Option<i64> = connection
+            .query_row(
+                "SELECT id FROM institutes WHERE name = ?1",
+                [institute],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id.is_some())
+    }
+
+    /// Restores all the stored schedules
+    pub fn load(&self) -> rusqlite::Result<Schedules> {
+        let connection = self.connection();
+        let mut select_institutes =
+            connection.prepare("SELECT id, name FROM institutes ORDER BY name")?;
+        let mut select_courses = connection
+            .prepare("SELECT id, name FROM courses WHERE institute_id = ?1 ORDER BY position")?;
+        let mut select_groups = connection
+            .prepare("SELECT id, name FROM groups WHERE course_id = ?1 ORDER BY position")?;
+        let mut select_subgroups = connection
+            .prepare("SELECT id, number FROM subgroups WHERE group_id = ?1 ORDER BY position")?;
+        let mut select_classes = connection.prepare(
+            "SELECT slots.day, slots.lesson, slots.week,
+                classes.name, classes.class_type, classes.class_type_name, teachers.name, rooms.name
+            FROM classes
+            JOIN slots ON slots.id = classes.slot_id
+            JOIN rooms ON rooms.id = classes.room_id
+            LEFT JOIN teachers ON teachers.id = classes.teacher_id
+            WHERE classes.subgroup_id = ?1",
+        )?;
+
+        let mut schedules = Schedules::default();
+        let institutes = select_institutes
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (institute_id, institute) in institutes {
+            let mut courses = Vec::new();
+            let course_rows = select_courses
+                .query_map([institute_id], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (course_id, course_name) in course_rows {
+                let mut groups = Vec::new();
+                let group_rows = select_groups
+                    .query_map([course_id], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                for (group_id, name) in group_rows {
+                    let subgroup_rows = select_subgroups
+                        .query_map([group_id], |row| {
+                            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<u8>>(1)?))
+                        })?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    let mut weeks = Vec::with_capacity(subgroup_rows.len());
+                    for (subgroup_id, number) in subgroup_rows {
+                        let mut week = Week::default();
+                        let mut rows = select_classes.query([subgroup_id])?;
+                        while let Some(row) = rows.next()? {
+                            let slot = Slot {
+                                day: row.get(0)?,
+                                lesson: row.get(1)?,
+                                week: week_from_sql(row.get(2)?),
+                            };
+                            set_class(&mut week, slot, class_from_row(row, 3)?);
+                        }
+                        weeks.push((number, week));
+                    }
+                    groups.push(GroupInfo::from_weeks(name, weeks));
+                }
+                courses.push(Course::new(course_name, groups));
+            }
+            schedules.insert(institute, courses);
+        }
+        Ok(schedules)
+    }
+
+    fn occurrences(&self, filter: &str, param: &dyn ToSql) -> rusqlite::Result<Vec<Occurrence>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached(&format!("{OCCURRENCES} WHERE {filter} {OCCURRENCES_ORDER}"))?;
+        let occurrences = statement.query_map([param], occurrence_from_row)?.collect();
+        occurrences
+    }
+
+    /// Classes taught by a teacher whose name contains `teacher`
+    pub fn teacher_classes(&self, teacher: &str) -> rusqlite::Result<Vec<Occurrence>> {
+        self.occurrences("instr(teachers.name, ?1) > 0", &teacher)
+    }
+
+    /// Classes that take place in a room
+    pub fn room_classes(&self, room: &str) -> rusqlite::Result<Vec<Occurrence>> {
+        self.occurrences("rooms.name = ?1", &room)
+    }
+
+    /// Rooms that are mentioned anywhere, but are not occupied during the `slot`
+    pub fn free_rooms(&self, slot: Slot) -> rusqlite::Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT name FROM rooms
+            WHERE id IN (SELECT room_id FROM classes)
+            AND id NOT IN (
+                SELECT classes.room_id FROM classes
+                JOIN slots ON slots.id = classes.slot_id
+                WHERE slots.day = ?1 AND slots.lesson = ?2 AND slots.week = ?3
+            )
+            ORDER BY name",
+        )?;
+        let rooms = statement
+            .query_map(
+                params![slot.day, slot.lesson, week_to_sql(slot.week)],
+                |row| row.get(0),
+            )?
+            .collect();
+        rooms
+    }
+}
+
+#[test]
+fn sqlite_round_trip() {
+    use calamine::{open_workbook, Xlsx};
+    let mut excel: Xlsx<_> = open_workbook("test/Test.xlsx").unwrap();
+    let parsed = crate::ExcelData::new(&mut excel).parse();
+
+    let store = SqliteStore::open_in_memory().unwrap();
+    assert!(!store.has_institute("test").unwrap());
+    store.insert_institute("test", &parsed).unwrap();
+    assert!(store.has_institute("test").unwrap());
+    let names = |table: &str| -> Vec<String> {
+        let connection = store.connection();
+        let mut statement = connection
+            .prepare(&format!("SELECT name FROM {table} ORDER BY name"))
+            .unwrap();
+        let names = statement.query_map([], |row| row.get(0)).unwrap();
+        names.collect::<rusqlite::Result<_>>().unwrap()
+    };
+    // The same classes in other rooms and with another teacher
+    let moved: Vec<Course> = serde_json::from_str(
+        &serde_json::to_string(&parsed)
+            .unwrap()
+            .replace("Teacher", "Moved")
+            .replace("\"Class", "\"Moved"),
+    )
+    .unwrap();
+    // Reinserting replaces the previous version, and forgets the names only it had
+    store.insert_institute("test", &moved).unwrap();
+    store.insert_institute("test", &parsed).unwrap();
+    store.insert_institute("removed", &moved).unwrap();
+    assert!(names("teachers").iter().any(|name| name.contains("Moved")));
+    store.remove_institute("removed").unwrap();
+    assert!(!store.has_institute("removed").unwrap());
+    assert!(!names("teachers").iter().any(|name| name.contains("Moved")));
+    assert!(!names("rooms").iter().any(|name| name.contains("Moved")));
+    // Reinserting the same institute again keeps the names it still has
+    store.insert_institute("test", &parsed).unwrap();
+
+    let mut expected = Schedules::default();
+    expected.insert(String::from("test"), parsed.to_vec());
+    let loaded = store.load().unwrap();
+    assert_eq!(loaded, expected);
+
+    assert_eq!(
+        store.teacher_classes("Teacher").unwrap(),
+        expected.teacher_classes("Teacher")
+    );
+    assert_eq!(store.teacher_classes("Teacher").unwrap().len(), 2);
+    assert_eq!(
+        store.room_classes("Class2").unwrap(),
+        expected.room_classes("Class2")
+    );
+
+    let monday_first = Slot {
+        day: 0,
+        lesson: 0,
+        week: WeekParity::Upper,
+    };
+    let free_rooms = store.free_rooms(monday_first).unwrap();
+    assert_eq!(free_rooms, expected.free_rooms(monday_first));
+    assert!(free_rooms.contains(&String::from("Class2")));
+    assert!(!free_rooms.contains(&String::from("Class")));
+}
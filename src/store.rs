@@ -0,0 +1,402 @@
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteStore;
+use crate::{interner::Interner, snapshot, Class, Course, GroupInfo, Week};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+/// A room of a building written as its letters and number, e.g. `Б-436` or `Л-550а`
+pub fn is_room(room: &str) -> bool {
+    let Some((building, number)) = room.split_once('-') else {
+        return false;
+    };
+    !building.is_empty()
+        && building.chars().all(char::is_alphabetic)
+        && number.starts_with(|char: char| char.is_ascii_digit())
+        && number.chars().all(char::is_alphanumeric)
+}
+
+/// Classes alternate between the upper and the lower week
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekParity {
+    Upper,
+    Lower,
+}
+
+/// Position of a class in a week
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Slot {
+    /// Monday is 0, Tuesday is 1, etc.
+    pub day: u8,
+    /// First lesson is 0, second is 1, etc.
+    pub lesson: u8,
+    pub week: WeekParity,
+}
+
+/// A class together with the group that has it and the time it takes place
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Occurrence {
+    pub institute: String,
+    pub course: String,
+    pub group: String,
+    /// [`None`] if the group isn't divided into subgroups
+    pub subgroup: Option<u8>,
+    pub slot: Slot,
+    pub class: Class,
+}
+
+/// Borrowed version of [`Occurrence`], used while scanning the schedules
+#[derive(Debug, Clone, Copy)]
+pub struct ClassRef<'a> {
+    pub institute: &'a str,
+    pub course: &'a Course,
+    pub group: &'a GroupInfo,
+    pub subgroup: Option<u8>,
+    pub slot: Slot,
+    pub class: &'a Class,
+}
+
+impl ClassRef<'_> {
+    pub fn to_occurrence(self) -> Occurrence {
+        Occurrence {
+            institute: self.institute.to_string(),
+            course: self.course.name.clone(),
+            group: self.group.name.clone(),
+            subgroup: self.subgroup,
+            slot: self.slot,
+            class: self.class.clone(),
+        }
+    }
+}
+
+/// Iterates over all the classes of a week together with their slots
+pub fn week_classes(week: &Week) -> impl Iterator<Item = (Slot, &Class)> {
+    week.iter().enumerate().flat_map(|(day, classes)| {
+        [
+            (WeekParity::Upper, &classes.upper_classes),
+            (WeekParity::Lower, &classes.lower_classes),
+        ]
+        .into_iter()
+        .flat_map(move |(week, classes)| {
+            classes
+                .iter()
+                .enumerate()
+                .filter_map(move |(lesson, class)| {
+                    class.as_ref().map(|class| {
+                        let slot = Slot {
+                            day: day as u8,
+                            lesson: lesson as u8,
+                            week,
+                        };
+                        (slot, class)
+                    })
+                })
+        })
+    })
+}
+
+/// Puts a class into its slot of a week
+pub fn set_class(week: &mut Week, slot: Slot, class: Class) {
+    let day = &mut week[slot.day as usize];
+    let classes = match slot.week {
+        WeekParity::Upper => &mut day.upper_classes,
+        WeekParity::Lower => &mut day.lower_classes,
+    };
+    classes[slot.lesson as usize] = Some(class);
+}
+
+/// All the parsed schedules, keyed by the name of the workbook they were parsed from
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+pub struct Schedules {
+    institutes: BTreeMap<String, Vec<Course>>,
+}
+
+impl Schedules {
+    /// Reads every json file in a directory, as written by [`crate::parse_schedules`]
+    pub fn load_dir(dir: &Path) -> io::Result<Self> {
+        let mut schedules = Self::default();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+                continue;
+            }
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let file = BufReader::new(File::open(&path)?);
+            let courses: Vec<Course> = serde_json::from_reader(file)?;
+            schedules.insert(name, courses);
+        }
+        Ok(schedules)
+    }
+
+    pub fn insert(&mut self, institute: String, courses: Vec<Course>) {
+        self.institutes.insert(institute, courses);
+    }
+
+    pub fn institute(&self, name: &str) -> Option<&[Course]> {
+        self.institutes.get(name).map(Vec::as_slice)
+    }
+
+    pub fn institutes(&self) -> impl Iterator<Item = (&str, &[Course])> {
+        self.institutes
+            .iter()
+            .map(|(name, courses)| (name.as_str(), courses.as_slice()))
+    }
+
+    /// Every class of every group in every institute
+    pub fn classes(&self) -> impl Iterator<Item = ClassRef<'_>> {
+        self.institutes().flat_map(|(institute, courses)| {
+            courses.iter().flat_map(move |course| {
+                course.groups.iter().flat_map(move |group| {
+                    group.weeks().into_iter().flat_map(move |(subgroup, week)| {
+                        week_classes(week).map(move |(slot, class)| ClassRef {
+                            institute,
+                            course,
+                            group,
+                            subgroup,
+                            slot,
+                            class,
+                        })
+                    })
+                })
+            })
+        })
+    }
+
+    fn classes_mut(&mut self) -> impl Iterator<Item = &mut Class> {
+        self.institutes
+            .values_mut()
+            .flatten()
+            .flat_map(|course| course.groups.iter_mut())
+            .flat_map(GroupInfo::weeks_mut)
+            .flat_map(|week| week.iter_mut())
+            .flat_map(|day| day.upper_classes.iter_mut().chain(&mut day.lower_classes))
+            .flatten()
+    }
+
+    /// Makes the equal names, teachers and rooms of all the classes share a single allocation
+    pub fn intern(&mut self) {
+        let mut interner = Interner::default();
+        for class in self.classes_mut() {
+            interner.intern_class(class);
+        }
+    }
+
+    /// Classes that satisfy the `predicate`, in the order of their slots
+    pub fn find_classes(&self, predicate: impl Fn(&Class) -> bool) -> Vec<Occurrence> {
+        let mut found: Vec<_> = self
+            .classes()
+            .filter(|class| predicate(class.class))
+            .map(ClassRef::to_occurrence)
+            .collect();
+        // Stable, so groups stay in the order they were parsed in
+        found.sort_by_key(|occurrence| occurrence.slot);
+        found
+    }
+
+    /// Classes taught by a teacher whose name contains `teacher`
+    pub fn teacher_classes(&self, teacher: &str) -> Vec<Occurrence> {
+        self.find_classes(|class| {
+            class
+                .teacher
+                .as_ref()
+                .map(|name| name.contains(teacher))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Classes that take place in a room
+    pub fn room_classes(&self, room: &str) -> Vec<Occurrence> {
+        self.find_classes(|class| &*class.room == room)
+    }
+
+    /// Rooms that are mentioned anywhere, but are not occupied during the `slot`
+    pub fn free_rooms(&self, slot: Slot) -> Vec<String> {
+        let mut rooms: BTreeMap<&str, bool> = BTreeMap::new();
+        for class in self.classes() {
+            let occupied = rooms.entry(&class.class.room).or_default();
+            *occupied |= class.slot == slot;
+        }
+        rooms
+            .into_iter()
+            .filter(|(_, occupied)| !occupied)
+            .map(|(room, _)| room.to_string())
+            .collect()
+    }
+
+    /// Lessons each room is occupied at during a day of a week.
+    /// Every room mentioned anywhere is included, the ones free the whole day without lessons.
+    /// Places that aren't rooms, like "Спортзал" or a distance learning mark, are left out
+    pub fn day_occupancy(&self, day: u8, week: WeekParity) -> BTreeMap<&str, BTreeSet<u8>> {
+        let mut rooms: BTreeMap<&str, BTreeSet<u8>> = BTreeMap::new();
+        for class in self.classes().filter(|class| is_room(&class.class.room)) {
+            let lessons = rooms.entry(&class.class.room).or_default();
+            if class.slot.day == day && class.slot.week == week {
+                lessons.insert(class.slot.lesson);
+            }
+        }
+        rooms
+    }
+
+    /// Every group of every institute
+    pub fn groups(&self) -> impl Iterator<Item = &GroupInfo> {
+        self.institutes
+            .values()
+            .flatten()
+            .flat_map(|course| &course.groups)
+    }
+
+    /// Finds a group by its name in any institute
+    pub fn find_group(&self, name: &str) -> Option<&GroupInfo> {
+        self.groups().find(|group| group.name == name)
+    }
+
+    /// Week of a group's subgroup, or of the whole group if it isn't divided into subgroups
+    pub fn find_week(&self, group: &str, subgroup: Option<u8>) -> Option<&Week> {
+        let weeks = self.find_group(group)?.weeks();
+        weeks
+            .iter()
+            .find(|(number, _)| number.is_none() || *number == subgroup)
+            .map(|(_, week)| *week)
+    }
+}
+
+/// Where the parsed schedules are persisted, besides the json files
+pub enum Backend {
+    /// Only the json files in `schedules/parsed`
+    Json,
+    /// Json files and an SQLite database
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+}
+
+impl Backend {
+    /// Picks a backend using the `MISISA_STORAGE` env variable, `json` by default.
+    /// The database path for the `sqlite` backend is taken from `MISISA_DATABASE`
+    pub fn from_env() -> io::Result<Self> {
+        match env::var("MISISA_STORAGE").as_deref() {
+            #[cfg(feature = "sqlite")]
+            Ok("sqlite") => {
+                let path = env::var("MISISA_DATABASE")
+                    .unwrap_or_else(|_| String::from("schedules/schedules.sqlite3"));
+                let store = SqliteStore::open(path).map_err(io::Error::other)?;
+                Ok(Self::Sqlite(store))
+            }
+            #[cfg(not(feature = "sqlite"))]
+            Ok("sqlite") => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The sqlite backend needs the sqlite feature",
+            )),
+            Ok("json") | Err(_) => Ok(Self::Json),
+            Ok(other) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown storage backend {other}"),
+            )),
+        }
+    }
+
+    /// Stores freshly parsed courses of a workbook
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    pub fn save(&self, institute: &str, courses: &[Course]) -> io::Result<()> {
+        match self {
+            Self::Json => Ok(()),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store
+                .insert_institute(institute, courses)
+                .map_err(io::Error::other),
+        }
+    }
+
+    /// Forgets the courses of a workbook that was deleted
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    pub fn remove(&self, institute: &str) -> io::Result<()> {
+        match self {
+            Self::Json => Ok(()),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.remove_institute(institute).map_err(io::Error::other),
+        }
+    }
+
+    /// Checks whether a workbook was already saved into the backend
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    pub fn contains(&self, institute: &str) -> io::Result<bool> {
+        match self {
+            Self::Json => Ok(true),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.has_institute(institute).map_err(io::Error::other),
+        }
+    }
+
+    /// Loads all the schedules, preferring the snapshot over the json files
+    /// if it's compatible and was taken of the same files.
+    /// Otherwise, or if the snapshot is broken, it is written again from the json files
+    pub fn load(&self, parsed_dir: &Path) -> io::Result<Schedules> {
+        match self {
+            Self::Json => {
+                let path = snapshot::path(parsed_dir);
+                let fingerprint = snapshot::fingerprint(parsed_dir)?;
+                match snapshot::read(&path, fingerprint) {
+                    Ok(Some(schedules)) => return Ok(schedules),
+                    Ok(None) => {}
+                    Err(error) => {
+                        eprintln!("Couldn't read the snapshot, loading the json files: {error}")
+                    }
+                }
+                let schedules = Schedules::load_dir(parsed_dir)?;
+                if let Err(error) = snapshot::write(&path, &schedules, fingerprint) {
+                    eprintln!("Couldn't write the snapshot: {error}");
+                }
+                Ok(schedules)
+            }
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.load().map_err(io::Error::other),
+        }
+    }
+}
+
+/// Schedules served by the app
+pub struct Store {
+    schedules: Schedules,
+    backend: Backend,
+}
+
+impl Store {
+    pub fn load(backend: Backend, parsed_dir: &Path) -> io::Result<Self> {
+        let mut schedules = backend.load(parsed_dir)?;
+        schedules.intern();
+        Ok(Self { schedules, backend })
+    }
+
+    pub const fn schedules(&self) -> &Schedules {
+        &self.schedules
+    }
+
+    pub fn teacher_classes(&self, teacher: &str) -> io::Result<Vec<Occurrence>> {
+        match &self.backend {
+            Backend::Json => Ok(self.schedules.teacher_classes(teacher)),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(store) => store.teacher_classes(teacher).map_err(io::Error::other),
+        }
+    }
+
+    pub fn room_classes(&self, room: &str) -> io::Result<Vec<Occurrence>> {
+        match &self.backend {
+            Backend::Json => Ok(self.schedules.room_classes(room)),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(store) => store.room_classes(room).map_err(io::Error::other),
+        }
+    }
+
+    pub fn free_rooms(&self, slot: Slot) -> io::Result<Vec<String>> {
+        match &self.backend {
+            Backend::Json => Ok(self.schedules.free_rooms(slot)),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(store) => store.free_rooms(slot).map_err(io::Error::other),
+        }
+    }
+}
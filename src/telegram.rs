@@ -0,0 +1,650 @@
+use crate::{
+    alisa::{self, InputType, Intent, Nlu, Slot, SlotValue, State, StringType},
+    skill::Action,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env,
+    fs::File,
+    future::Future,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Header with the secret token set together with the webhook
+pub const SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+const HELP: &str = "Я знаю расписание МИСиС. \
+    /setgroup БИВТ-21-15 — запомнить группу, /today и /tomorrow — пары на сегодня и завтра, \
+    /week — пары на неделе, /teacher Иванов — где сейчас преподаватель. \
+    Можно спросить и словами, например, когда завтра первая пара.";
+const NO_TEACHER: &str = "Напишите фамилию преподавателя, например, /teacher Иванов.";
+
+/// Longest `callback_data` of a button Telegram accepts, in bytes
+const MAX_CALLBACK_DATA: usize = 64;
+
+/// Time a Bot API call has to be answered in, so a hanging one doesn't hold the update
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub message_id: u64,
+    pub chat: Chat,
+    /// Missing for stickers, photos and other messages without text
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A press of an inline keyboard button
+pub struct CallbackQuery {
+    pub id: String,
+    /// The message with the button, missing if it is too old
+    pub message: Option<Message>,
+    /// `callback_data` of the button
+    pub data: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// What Telegram posts to the bot's webhook, only the used kinds of updates are kept
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+    pub callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InlineKeyboardButton {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InlineKeyboardMarkup {
+    pub inline_keyboard: Vec<Vec<InlineKeyboardButton>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Parameters of the `sendMessage` method
+pub struct SendMessage {
+    pub chat_id: i64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_markup: Option<InlineKeyboardMarkup>,
+}
+
+impl SendMessage {
+    /// The skill's answer with its buttons turned into an inline keyboard
+    fn from_alisa(chat_id: i64, response: &alisa::Response) -> Self {
+        let buttons: Vec<InlineKeyboardButton> = response
+            .buttons
+            .iter()
+            .filter_map(|button| {
+                let callback_data = button.payload.as_ref().map(Value::to_string);
+                if callback_data
+                    .as_ref()
+                    .map_or(button.url.is_none(), |data| data.len() > MAX_CALLBACK_DATA)
+                {
+                    return None;
+                }
+                Some(InlineKeyboardButton {
+                    text: button.title.clone(),
+                    callback_data,
+                    url: button.url.clone(),
+                })
+            })
+            .collect();
+        Self {
+            chat_id,
+            text: response.text.clone(),
+            reply_markup: (!buttons.is_empty()).then(|| InlineKeyboardMarkup {
+                inline_keyboard: vec![buttons],
+            }),
+        }
+    }
+}
+
+/// Client of the Bot API
+pub struct Bot {
+    client: reqwest::Client,
+    /// `https://api.telegram.org` or a stand-in for it
+    api_url: String,
+    token: String,
+    /// Has to come in the [`SECRET_HEADER`] of the updates
+    secret: String,
+}
+
+impl Bot {
+    pub fn new(
+        api_url: impl Into<String>,
+        token: impl Into<String>,
+        secret: impl Into<String>,
+    ) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .expect("Couldn't build the HTTP client");
+        Self {
+            client,
+            api_url: api_url.into(),
+            token: token.into(),
+            secret: secret.into(),
+        }
+    }
+
+    /// Reads `MISISA_TELEGRAM_TOKEN`, `MISISA_TELEGRAM_API` and `MISISA_TELEGRAM_SECRET`,
+    /// [`None`] if there is no token or no secret, so anyone could post updates
+    pub fn from_env() -> Option<Self> {
+        let token = env::var("MISISA_TELEGRAM_TOKEN").ok()?;
+        let Ok(secret) = env::var("MISISA_TELEGRAM_SECRET") else {
+            eprintln!(
+                "MISISA_TELEGRAM_TOKEN is set without MISISA_TELEGRAM_SECRET, the bot is off"
+            );
+            return None;
+        };
+        let api_url = env::var("MISISA_TELEGRAM_API")
+            .unwrap_or_else(|_| String::from("https://api.telegram.org"));
+        Some(Self::new(api_url, token, secret))
+    }
+
+    /// The update came with the secret in its [`SECRET_HEADER`]
+    pub fn is_authentic(&self, secret: Option<&str>) -> bool {
+        // Digests are compared, so the time the comparison takes tells nothing about the secret
+        let digest = |secret: &str| Sha256::digest(secret.as_bytes());
+        secret.is_some_and(|secret| digest(secret) == digest(&self.secret))
+    }
+
+    async fn call(&self, method: &str, parameters: &impl Serialize) -> Result<(), reqwest::Error> {
+        let url = format!(
+            "{}/bot{}/{method}",
+            self.api_url.trim_end_matches('/'),
+            self.token
+        );
+        self.client
+            .post(url)
+            .json(parameters)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn send_message(&self, message: &SendMessage) -> Result<(), reqwest::Error> {
+        self.call("sendMessage", message).await
+    }
+
+    /// Stops the spinner on the pressed button
+    pub async fn answer_callback_query(&self, id: &str) -> Result<(), reqwest::Error> {
+        self.call("answerCallbackQuery", &json!({ "callback_query_id": id }))
+            .await
+    }
+}
+
+/// States the skill keeps for each chat, like Dialogs keep them for a user
+#[derive(Debug, Default)]
+pub struct Chats {
+    /// JSON file the states are saved to, they are only kept in memory if [`None`]
+    path: Option<PathBuf>,
+    states: Mutex<HashMap<i64, State>>,
+    /// How many times the states were changed
+    changes: AtomicU64,
+    /// The change the saved file has, so an older one doesn't overwrite it
+    saved: Arc<Mutex<u64>>,
+}
+
+impl Chats {
+    /// Reads the states from a JSON file, there are none if there is no such file
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let states = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Self {
+            path: Some(path),
+            states: Mutex::new(states),
+            ..Self::default()
+        })
+    }
+
+    /// Reads `MISISA_TELEGRAM_CHATS`, `telegram.json` by default
+    pub fn from_env() -> io::Result<Self> {
+        let path = env::var_os("MISISA_TELEGRAM_CHATS")
+            .map_or_else(|| PathBuf::from("telegram.json"), PathBuf::from);
+        Self::load(path)
+    }
+
+    fn state(&self, chat: i64) -> State {
+        let states = self.states.lock().unwrap();
+        states.get(&chat).cloned().unwrap_or_default()
+    }
+
+    /// Keeps the states the skill set in its answer and saves all of them on a blocking thread,
+    /// the file is only written when the user or the application state changed
+    async fn update(&self, chat: i64, response: &alisa::WebhookResponse) -> io::Result<()> {
+        let (change, contents) = {
+            let mut states = self.states.lock().unwrap();
+            states.entry(chat).or_default().apply(response);
+            if response.user_state_update.is_none() && response.application_state.is_none() {
+                return Ok(());
+            }
+            let change = self.changes.fetch_add(1, Ordering::Relaxed) + 1;
+            (change, serde_json::to_vec(&*states)?)
+        };
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let saved = self.saved.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut saved = saved.lock().unwrap();
+            // A later change was saved while this one waited
+            if *saved > change {
+                return Ok(());
+            }
+            write_replacing(&path, &contents)?;
+            *saved = change;
+            Ok(())
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+/// Writes into a temporary file first and puts it in place of the old one,
+/// so the file is never left half written
+fn write_replacing(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary_path = path.with_extension("json.tmp");
+    std::fs::write(&temporary_path, contents)?;
+    std::fs::rename(temporary_path, path)
+}
+
+/// What an update asks for
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    /// A question to the skill, translated into Alice's request
+    Skill(Box<alisa::WebhookRequest>),
+    /// Answered by the bot itself
+    Reply(&'static str),
+}
+
+/// `/setgroup@misisa_bot БИВТ-21-15` is `("setgroup", "БИВТ-21-15")`
+fn command(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('/')?;
+    let (name, arguments) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = name.split_once('@').map_or(name, |(name, _)| name);
+    Some((name, arguments.trim()))
+}
+
+/// A message of the user as Alice would send it, or a press of a button with the `payload`
+fn alisa_request(
+    chat: i64,
+    message_id: u64,
+    text: &str,
+    nlu: Nlu,
+    payload: Option<Value>,
+    state: State,
+) -> alisa::WebhookRequest {
+    let request_type = if payload.is_some() {
+        InputType::ButtonPressed
+    } else {
+        InputType::SimpleUtterance
+    };
+    alisa::WebhookRequest {
+        meta: alisa::Meta {
+            locale: String::from("ru-RU"),
+            timezone: String::from("Europe/Moscow"),
+            client_id: String::from("telegram"),
+            interfaces: alisa::Interfaces {
+                screen: Some(json!({})),
+                ..alisa::Interfaces::default()
+            },
+        },
+        request: alisa::Request {
+            command: nlu.tokens.join(" "),
+            original_utterance: String::from(text),
+            markup: None,
+            nlu,
+            payload,
+            request_type,
+            show_type: None,
+        },
+        session: alisa::Session {
+            message_id,
+            session_id: chat.to_string(),
+            skill_id: String::from("telegram"),
+            // The chat is always known, so the skill keeps the group in the user state
+            user: Some(alisa::User {
+                user_id: chat.to_string(),
+            }),
+            application: alisa::Application {
+                application_id: chat.to_string(),
+            },
+            new: false,
+        },
+        state,
+        version: String::from("1.0"),
+    }
+}
+
+/// The chat an update came from and what it asks for,
+/// [`None`] for the updates the bot doesn't answer
+fn translate(update: &Update, chats: &Chats) -> serde_json::Result<Option<(i64, Incoming)>> {
+    if let Some(callback) = &update.callback_query {
+        // Buttons of the messages that are too old or with foreign data aren't answered
+        let payload = callback
+            .data
+            .as_deref()
+            .and_then(|data| serde_json::from_str(data).ok());
+        let (Some(message), Some(payload)) = (&callback.message, payload) else {
+            return Ok(None);
+        };
+        let chat = message.chat.id;
+        let request = alisa_request(
+            chat,
+            message.message_id,
+            "",
+            Nlu::default(),
+            Some(payload),
+            chats.state(chat),
+        );
+        return Ok(Some((chat, Incoming::Skill(Box::new(request)))));
+    }
+    let Some(message) = &update.message else {
+        return Ok(None);
+    };
+    let Some(text) = message.text.as_deref() else {
+        return Ok(None);
+    };
+    let chat = message.chat.id;
+    let ask = |text: &str, nlu: Nlu, payload: Option<Value>| {
+        let request = alisa_request(
+            chat,
+            message.message_id,
+            text,
+            nlu,
+            payload,
+            chats.state(chat),
+        );
+        Ok(Some((chat, Incoming::Skill(Box::new(request)))))
+    };
+    let press = |action: Action| ask(text, Nlu::default(), Some(serde_json::to_value(action)?));
+    match command(text) {
+        None => ask(text, Nlu::from_text(text), None),
+        Some(("today", _)) => press(Action::Today),
+        Some(("tomorrow", _)) => press(Action::Tomorrow),
+        Some(("week", _)) => press(Action::Week),
+        Some(("setgroup", "")) => ask("смени группу", Nlu::from_text("смени группу"), None),
+        Some(("setgroup", group)) => {
+            let text = format!("группа {group}");
+            ask(&text, Nlu::from_text(&text), None)
+        }
+        Some(("teacher", "")) => Ok(Some((chat, Incoming::Reply(NO_TEACHER)))),
+        Some(("teacher", teacher)) => {
+            let mut nlu = Nlu::from_text(teacher);
+            let slot = Slot {
+                tokens: None,
+                value: SlotValue::String {
+                    slot_type: StringType::String,
+                    value: String::from(teacher),
+                },
+            };
+            let intent = Intent {
+                slots: HashMap::from([(String::from("teacher"), slot)]),
+            };
+            nlu.intents.insert(String::from("teacher.where"), intent);
+            ask(teacher, nlu, None)
+        }
+        Some(_) => Ok(Some((chat, Incoming::Reply(HELP)))),
+    }
+}
+
+#[derive(Debug)]
+pub enum UpdateError {
+    /// The update doesn't fit Alice's request
+    Translation(serde_json::Error),
+    Request(reqwest::Error),
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Translation(error) => write!(f, "Couldn't translate the update: {error}"),
+            Self::Request(error) => write!(f, "Bot API request failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl From<serde_json::Error> for UpdateError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Translation(error)
+    }
+}
+
+impl From<reqwest::Error> for UpdateError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error)
+    }
+}
+
+/// Answers an update with the skill's `answer` and sends it to the chat
+pub async fn handle_update<F, Fut>(
+    bot: &Bot,
+    chats: &Chats,
+    update: &Update,
+    answer: F,
+) -> Result<(), UpdateError>
+where
+    F: FnOnce(alisa::WebhookRequest) -> Fut,
+    Fut: Future<Output = alisa::WebhookResponse>,
+{
+    if let Some(callback) = &update.callback_query {
+        // The spinner stops by itself, the answer is still worth sending
+        if let Err(error) = bot.answer_callback_query(&callback.id).await {
+            eprintln!(
+                "Couldn't answer the callback query {}: {error}",
+                callback.id
+            );
+        }
+    }
+    let Some((chat, incoming)) = translate(update, chats)? else {
+        return Ok(());
+    };
+    let message = match incoming {
+        Incoming::Reply(text) => SendMessage {
+            chat_id: chat,
+            text: String::from(text),
+            reply_markup: None,
+        },
+        Incoming::Skill(request) => {
+            let response = answer(*request).await;
+            if let Err(error) = chats.update(chat, &response).await {
+                eprintln!("Couldn't save the state of the chat {chat}: {error}");
+            }
+            SendMessage::from_alisa(chat, &response.response)
+        }
+    };
+    Ok(bot.send_message(&message).await?)
+}
+
+#[test]
+fn updates_are_translated() {
+    let chats = Chats::default();
+    let message = |text: &str| -> Update {
+        serde_json::from_value(json!({
+            "update_id": 10,
+            "message": {
+                "message_id": 5,
+                "from": { "id": 42, "is_bot": false, "first_name": "Иван" },
+                "chat": { "id": 42, "type": "private" },
+                "date": 1_662_350_400,
+                "text": text
+            }
+        }))
+        .unwrap()
+    };
+    let request = |text: &str| match translate(&message(text), &chats).unwrap() {
+        Some((42, Incoming::Skill(request))) => request.request,
+        other => panic!("{text} isn't a question to the skill: {other:?}"),
+    };
+
+    assert_eq!(
+        request("/tomorrow").payload::<Action>(),
+        Some(Action::Tomorrow)
+    );
+    assert_eq!(
+        request("/today@misisa_bot").payload::<Action>(),
+        Some(Action::Today)
+    );
+    assert_eq!(
+        request("/setgroup БИВТ-21-15").nlu.tokens,
+        ["группа", "бивт-21-15"]
+    );
+    let teacher = request("/teacher Иванов И. И.");
+    assert_eq!(
+        teacher.nlu.intents["teacher.where"]
+            .slot("teacher")
+            .and_then(alisa::Slot::string),
+        Some("Иванов И. И.")
+    );
+    assert_eq!(request("Когда завтра первая пара?").nlu.entities.len(), 1);
+    assert_eq!(
+        translate(&message("/teacher"), &chats).unwrap(),
+        Some((42, Incoming::Reply(NO_TEACHER)))
+    );
+    assert_eq!(
+        translate(&message("/start"), &chats).unwrap(),
+        Some((42, Incoming::Reply(HELP)))
+    );
+}
+
+#[tokio::test]
+async fn answers_are_sent() {
+    use std::{net::Ipv4Addr, sync::Arc};
+    use warp::Filter;
+
+    // A stand-in for the Bot API that records the called methods and their parameters
+    let received: Arc<Mutex<Vec<(String, Value)>>> = Arc::default();
+    let stand_in = warp::post()
+        .and(warp::path!("bot123:token" / String))
+        .and(warp::body::json())
+        .map({
+            let received = received.clone();
+            move |method: String, body: Value| {
+                // Telegram refuses to answer the queries that are too old
+                let status = if body["callback_query_id"] == "expired" {
+                    warp::http::StatusCode::BAD_REQUEST
+                } else {
+                    warp::http::StatusCode::OK
+                };
+                received.lock().unwrap().push((method, body));
+                let reply = warp::reply::json(&json!({ "ok": true, "result": true }));
+                warp::reply::with_status(reply, status)
+            }
+        });
+    let (address, server) = warp::serve(stand_in).bind_ephemeral((Ipv4Addr::LOCALHOST, 0));
+    tokio::spawn(server);
+
+    let bot = Bot::new(format!("http://{address}/"), "123:token", "secret");
+    assert!(bot.is_authentic(Some("secret")));
+    assert!(!bot.is_authentic(Some("secrets")));
+    assert!(!bot.is_authentic(None));
+    let chats = Chats::default();
+    let update: Update = serde_json::from_value(json!({
+        "update_id": 11,
+        "callback_query": {
+            "id": "4382bfdwdsb323b2d9",
+            "from": { "id": 42, "is_bot": false, "first_name": "Иван" },
+            "message": { "message_id": 6, "chat": { "id": 42, "type": "private" } },
+            "chat_instance": "-2938475",
+            "data": "{\"action\":\"week\"}"
+        }
+    }))
+    .unwrap();
+    let answer = |request: alisa::WebhookRequest| async move {
+        assert_eq!(request.request.payload::<Action>(), Some(Action::Week));
+        let mut response = alisa::WebhookResponse::new(alisa::Response {
+            buttons: vec![alisa::Button::with_payload("Завтра", &Action::Tomorrow)],
+            ..alisa::Response::new("На этой неделе у вас нет пар.")
+        });
+        response.user_state_update = Some(json!({ "group": "БИВТ-21-15" }));
+        response
+    };
+    handle_update(&bot, &chats, &update, answer).await.unwrap();
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        [
+            (
+                String::from("answerCallbackQuery"),
+                json!({ "callback_query_id": "4382bfdwdsb323b2d9" })
+            ),
+            (
+                String::from("sendMessage"),
+                json!({
+                    "chat_id": 42,
+                    "text": "На этой неделе у вас нет пар.",
+                    "reply_markup": {
+                        "inline_keyboard": [[
+                            { "text": "Завтра", "callback_data": "{\"action\":\"tomorrow\"}" }
+                        ]]
+                    }
+                })
+            )
+        ]
+    );
+    assert_eq!(chats.state(42).user, json!({ "group": "БИВТ-21-15" }));
+
+    let mut update = update;
+    update.callback_query.as_mut().unwrap().id = String::from("expired");
+    received.lock().unwrap().clear();
+    handle_update(&bot, &chats, &update, answer).await.unwrap();
+    let methods: Vec<String> = received
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(method, _)| method.clone())
+        .collect();
+    assert_eq!(methods, ["answerCallbackQuery", "sendMessage"]);
+}
+
+#[tokio::test]
+async fn chats_are_saved() {
+    let path = env::temp_dir().join(format!("misisa-telegram-{}.json", std::process::id()));
+    let chats = Chats::load(path.clone()).unwrap();
+    let mut response = alisa::WebhookResponse::new(alisa::Response::new("Запомнила."));
+    response.user_state_update = Some(json!({ "group": "БИВТ-21-15" }));
+    chats.update(42, &response).await.unwrap();
+    response.user_state_update = Some(json!({ "subgroup": 1 }));
+    chats.update(42, &response).await.unwrap();
+
+    let loaded = Chats::load(path.clone()).unwrap();
+    assert_eq!(
+        loaded.state(42).user,
+        json!({ "group": "БИВТ-21-15", "subgroup": 1 })
+    );
+    assert!(!path.with_extension("json.tmp").exists());
+
+    std::fs::remove_file(&path).unwrap();
+    let response = alisa::WebhookResponse::new(alisa::Response::new("Завтра пар нет."));
+    chats.update(42, &response).await.unwrap();
+    assert!(!path.exists());
+}
@@ -0,0 +1,347 @@
+use crate::diff::GroupChanges;
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{fs::File, io, path::Path, time::Duration};
+
+/// Header with the hex encoded HMAC-SHA256 of the body, prefixed with `sha256=`
+pub const SIGNATURE_HEADER: &str = "X-Misisa-Signature";
+
+/// A URL that is notified about the changes in the schedule
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Webhook {
+    pub url: String,
+    /// Only the changes of these groups are sent, all of them if [`None`]
+    #[serde(default)]
+    pub groups: Option<Vec<String>>,
+    /// Key for the signature header, the requests aren't signed if [`None`]
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// How the failed deliveries are retried
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct Retry {
+    /// Total number of attempts, including the first one
+    pub attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt
+    pub initial_delay_ms: u64,
+    /// Time an attempt may take, a webhook that doesn't answer in time is retried
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+const fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+/// Time to connect to a webhook, unless the whole attempt has less time
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay_ms: 1000,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, Clone)]
+pub struct WebhooksConfig {
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    #[serde(default)]
+    pub retry: Retry,
+}
+
+impl WebhooksConfig {
+    /// Reads the config from a json file, no webhooks are configured if there is no such file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Body of a webhook request
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct Payload<'a> {
+    pub changes: &'a [GroupChanges],
+}
+
+/// Signs a body with a secret, as sent in the [`SIGNATURE_HEADER`]
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Debug)]
+pub enum DeliveryError {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+}
+
+impl std::fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(error) => write!(f, "Request failed: {error}"),
+            Self::Status(status) => write!(f, "Webhook responded with {status}"),
+        }
+    }
+}
+
+impl std::error::Error for DeliveryError {}
+
+/// Posts the body to a webhook, retrying with an exponential backoff
+/// Client errors, except for 429 Too Many Requests, are not retried
+async fn deliver(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    body: Vec<u8>,
+    retry: Retry,
+) -> Result<(), DeliveryError> {
+    let signature = webhook.secret.as_ref().map(|secret| sign(secret, &body));
+    let mut delay = Duration::from_millis(retry.initial_delay_ms);
+    let mut attempt = 1;
+    loop {
+        let mut request = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(DeliveryError::Status(status));
+                }
+                DeliveryError::Status(status)
+            }
+            Err(error) => DeliveryError::Request(error),
+        };
+        if attempt >= retry.attempts {
+            return Err(error);
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Sends the changes to every webhook that is interested in them
+/// Returns the errors of the webhooks that couldn't be notified
+pub async fn notify(
+    config: &WebhooksConfig,
+    changes: &[GroupChanges],
+) -> Vec<(String, DeliveryError)> {
+    let timeout = Duration::from_millis(config.retry.timeout_ms);
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+        .build()
+        .expect("Couldn't build the HTTP client");
+    let deliveries = config.webhooks.iter().filter_map(|webhook| {
+        let changes: Vec<GroupChanges> = changes
+            .iter()
+            .filter(|changes| {
+                webhook
+                    .groups
+                    .as_ref()
+                    .map(|groups| groups.contains(&changes.group))
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        let body = serde_json::to_vec(&Payload { changes: &changes }).unwrap();
+        let client = &client;
+        Some(async move {
+            deliver(client, webhook, body, config.retry)
+                .await
+                .map_err(|error| (webhook.url.clone(), error))
+        })
+    });
+    // A webhook that is down and being retried doesn't hold the others
+    join_all(deliveries)
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+}
+
+#[tokio::test]
+async fn webhooks_are_delivered() {
+    use std::{
+        net::Ipv4Addr,
+        sync::{Arc, Mutex},
+    };
+    use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+    // A stand-in that fails the first request and records the signatures and bodies of the rest
+    type Received = Vec<(Option<String>, Bytes)>;
+    let received: Arc<Mutex<Received>> = Arc::default();
+    let calls = Arc::new(Mutex::new(0));
+    let stand_in = warp::post()
+        .and(warp::header::optional::<String>(SIGNATURE_HEADER))
+        .and(warp::body::bytes())
+        .map({
+            let received = received.clone();
+            move |signature: Option<String>, body: Bytes| {
+                let mut calls = calls.lock().unwrap();
+                *calls += 1;
+                if *calls == 1 {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                received.lock().unwrap().push((signature, body));
+                StatusCode::OK
+            }
+        });
+    let (address, server) = warp::serve(stand_in).bind_ephemeral((Ipv4Addr::LOCALHOST, 0));
+    tokio::spawn(server);
+
+    let changes = |group: &str| GroupChanges {
+        institute: String::from("itkn"),
+        course: String::from("2 курс"),
+        group: String::from(group),
+        subgroup: Some(1),
+        changes: Vec::new(),
+    };
+    let url = format!("http://{address}/hook");
+    let config = WebhooksConfig {
+        webhooks: vec![
+            Webhook {
+                url: url.clone(),
+                groups: Some(vec![String::from("БИВТ-21-15")]),
+                secret: Some(String::from("secret")),
+            },
+            Webhook {
+                url: url.clone(),
+                groups: Some(vec![String::from("БПМ-21-1")]),
+                secret: None,
+            },
+        ],
+        retry: Retry {
+            attempts: 3,
+            initial_delay_ms: 10,
+            ..Retry::default()
+        },
+    };
+    let all_changes = [changes("БИВТ-21-15"), changes("БИВТ-21-16")];
+    let errors = notify(&config, &all_changes).await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let received = received.lock().unwrap();
+    // The second webhook isn't interested in any of the groups
+    assert_eq!(received.len(), 1);
+    let (signature, body) = &received[0];
+    assert_eq!(signature.as_deref(), Some(sign("secret", body).as_str()));
+    let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(payload["changes"].as_array().unwrap().len(), 1);
+    assert_eq!(payload["changes"][0]["group"], "БИВТ-21-15");
+}
+
+#[tokio::test]
+async fn slow_webhooks_dont_hold_others() {
+    use std::{
+        net::Ipv4Addr,
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+    use warp::{http::StatusCode, Filter};
+
+    let start = Instant::now();
+    let delivered = Arc::new(Mutex::new(None));
+    let down = warp::path!("down").map(|| StatusCode::SERVICE_UNAVAILABLE);
+    let up = warp::path!("up").map({
+        let delivered = delivered.clone();
+        move || {
+            *delivered.lock().unwrap() = Some(start.elapsed());
+            StatusCode::OK
+        }
+    });
+    let (address, server) =
+        warp::serve(warp::post().and(down.or(up))).bind_ephemeral((Ipv4Addr::LOCALHOST, 0));
+    tokio::spawn(server);
+
+    let webhook = |path: &str| Webhook {
+        url: format!("http://{address}/{path}"),
+        groups: None,
+        secret: None,
+    };
+    let config = WebhooksConfig {
+        webhooks: vec![webhook("down"), webhook("up")],
+        retry: Retry {
+            attempts: 3,
+            initial_delay_ms: 300,
+            ..Retry::default()
+        },
+    };
+    let changes = [GroupChanges {
+        institute: String::from("itkn"),
+        course: String::from("2 курс"),
+        group: String::from("БИВТ-21-15"),
+        subgroup: None,
+        changes: Vec::new(),
+    }];
+    let errors = notify(&config, &changes).await;
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].0.ends_with("/down"));
+    // The first webhook spends 900 ms on its retries
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    assert!(delivered.lock().unwrap().unwrap() < Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn hanging_webhooks_time_out() {
+    use std::{net::Ipv4Addr, time::Instant};
+    use warp::{http::StatusCode, Filter};
+
+    // A stand-in that answers long after the attempts time out
+    let hanging = warp::post().then(|| async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        StatusCode::OK
+    });
+    let (address, server) = warp::serve(hanging).bind_ephemeral((Ipv4Addr::LOCALHOST, 0));
+    tokio::spawn(server);
+
+    let config = WebhooksConfig {
+        webhooks: vec![Webhook {
+            url: format!("http://{address}/hook"),
+            groups: None,
+            secret: None,
+        }],
+        retry: Retry {
+            attempts: 2,
+            initial_delay_ms: 10,
+            timeout_ms: 100,
+        },
+    };
+    let changes = [GroupChanges {
+        institute: String::from("itkn"),
+        course: String::from("2 курс"),
+        group: String::from("БИВТ-21-15"),
+        subgroup: None,
+        changes: Vec::new(),
+    }];
+    let start = Instant::now();
+    let errors = notify(&config, &changes).await;
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(errors.len(), 1);
+    assert!(
+        matches!(&errors[0].1, DeliveryError::Request(error) if error.is_timeout()),
+        "{:?}",
+        errors[0].1
+    );
+}